@@ -1,24 +1,45 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::{Range, RangeInclusive};
+use std::path::PathBuf;
 use std::sync::{Arc, Weak};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use serenity::framework::standard::{CommandResult, Args, HelpOptions, CommandGroup, help_commands};
-use serenity::framework::standard::macros::{command, group, hook, help};
-use serenity::http::{Http, CacheHttp};
+use serenity::framework::standard::{CommandResult, Args, HelpOptions, CommandGroup, help_commands, CommandOptions, Reason, DispatchError};
+use serenity::framework::standard::macros::{command, group, hook, help, check};
 use serenity::model::guild::Guild;
 use serenity::{prelude::*, async_trait};
 use serenity::Result;
+use serenity::Error as SerenityError;
+use serenity::http::error::Error as HttpError;
+use serde_json::Value;
+use serenity::builder::{CreateComponents, CreateEmbed};
 use serenity::model::channel::Message;
-use serenity::model::id::{ChannelId, UserId};
-use songbird::{TrackEvent, Event, EventHandler as VoiceEventHandler, EventContext, Call};
+use serenity::model::gateway::Activity;
+use serenity::model::id::{ChannelId, GuildId, MessageId, UserId};
+use serenity::model::interactions::InteractionResponseType;
+use serenity::model::interactions::message_component::{ButtonStyle, MessageComponentInteraction};
+use songbird::{TrackEvent, Event, EventHandler as VoiceEventHandler, EventContext, Call, Songbird};
+use songbird::error::JoinError;
+use songbird::tracks::{LoopState, PlayMode, TrackHandle};
 use tokio::sync::MutexGuard;
 
-use crate::{ApiAccessKey, ApiAccess, PerServerQueue, PerServerQueueAccessKey, Song, YouTubeVideo, SongSource, ServerQueue};
+use crate::{ApiAccessKey, ApiAccess, EqPreset, MAX_ALTERNATES, MAX_TITLE_LENGTH, PerServerQueue, PerServerQueueAccessKey, Song, SongSource, ServerQueue, SourceKind, SpotifyTrack, YtdlConfig, YtdlConfigKey, ffprobe_duration, truncate, ytdl_download, ytdl_info, ytdl_playlist_info};
+use crate::resume::{self, SavedQueue, SavedSong};
+use crate::search::{QuotaExceeded, SearchProvider, SearchProviderKey};
+use crate::messages::{Locale, Reply};
+use crate::settings::{GuildSettings, GuildSettingsKey, GuildSettingsStore, LiveQueue};
+use crate::stats::{SourceStats, SourceStatsKey};
+use crate::spotify_cache::{SpotifyCache, SpotifyCacheKey, SpotifyMatch};
 
 #[group("general")]
-#[commands(summon, play, now_playing, queue, skip)]
+#[commands(summon, reconnect, play, search, history, queuefront, wrongsong, now_playing, queue, queuefind, jumpto, skip, pause, resume, move_song, remove, myfirst, clear, cleardupes, swapnext, undo, volume, repeat, autoplay, liveprogress, clearnp, eq, livequeue, sources, disable, enable, prefix, language, autojoin, samechannel, stickynp, skipdelay, musicchannel, idletimeout, selecttimeout, movecooldown, plaintext, pingrequester, reactions)]
 pub(crate) struct General;
 
+#[group("owner")]
+#[owners_only]
+#[commands(guilds, leaveguild, savesong, pauseall, stopall)]
+pub(crate) struct Owner;
+
 fn user_vc(guild: &Guild, user: &UserId) -> Option<ChannelId> {
     guild
         .voice_states
@@ -26,28 +47,107 @@ fn user_vc(guild: &Guild, user: &UserId) -> Option<ChannelId> {
         .and_then(|voice_state| voice_state.channel_id)
 }
 
+// Another deployment of this bot already in the channel (ex. mid-deploy) would play over this one.
+// Only the bots listed in OTHER_INSTANCE_IDS count, other bots (ex. recorders) are fine to join
+async fn other_instance_in(ctx: &Context, guild: &Guild, channel_id: ChannelId) -> bool {
+    let queues = get_queues(ctx).await;
+    guild.voice_states
+        .values()
+        .any(|state| state.channel_id == Some(channel_id) && queues.other_instances.contains(&state.user_id))
+}
+
+// Passes for members with a role named "DJ" or the Manage Server permission
+#[check]
+#[name = "DJ"]
+async fn dj_check(ctx: &Context, msg: &Message, _: &mut Args, _: &CommandOptions) -> std::result::Result<(), Reason> {
+    if is_dj(ctx, msg).await {
+        Ok(())
+    } else {
+        Err(Reason::User(Reply::NeedDj.render(locale(ctx, msg.guild_id).await)))
+    }
+}
+
+async fn is_dj(ctx: &Context, msg: &Message) -> bool {
+    let guild = match msg.guild(&ctx.cache).await {
+        Some(guild) => guild,
+        None => return false,
+    };
+    let has_role = msg.member(ctx).await.is_ok_and(|member| {
+        member.roles.iter().any(|role| guild.roles.get(role).is_some_and(|role| role.name.eq_ignore_ascii_case("DJ")))
+    });
+    let is_manager = guild.member_permissions(ctx, msg.author.id).await.is_ok_and(|perms| perms.manage_guild());
+
+    has_role || is_manager
+}
+
+// Anyone can change the songs they queued, only DJs can change everyone else's
+async fn can_edit_song(ctx: &Context, msg: &Message, song: &Song) -> bool {
+    song.author == msg.author.tag() || is_dj(ctx, msg).await
+}
+
 #[command]
 #[only_in(guilds)]
 #[aliases("join")]
 async fn summon(ctx: &Context, msg: &Message) -> CommandResult {
-    let guild = msg.guild(&ctx.cache).await.unwrap();
-    let guild_id = guild.id;
+    let (manager, guild, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
 
     // if the author is in a vc
     if let Some(author_vc) = user_vc(&guild, &msg.author.id) {
-        // get songbird manager from the type map
-        let manager = songbird::get(ctx)
-            .await
-            .expect("Songbird voice client placed in at initialization.")
-            .clone();
+        // moving restarts the audio, so the bot can't be pulled between channels over and over
+        let bot_vc = user_vc(&guild, &ctx.cache.current_user_id().await);
+        let moving = bot_vc.is_some_and(|bot_vc| bot_vc != author_vc);
+        if moving {
+            let cooldown = Duration::from_secs(get_settings(ctx).await.get(guild.id, |settings| settings.move_cooldown).await);
+            let last_moved = queue_lock.lock().await.last_moved;
+            if let Some(wait) = last_moved.and_then(|moved| cooldown.checked_sub(moved.elapsed())) {
+                respond(ctx, msg, Reply::MoveCooldown(wait.as_secs() + 1)).await;
+                return Ok(());
+            }
+        }
+
+        if manager.get(guild.id).is_none() && other_instance_in(ctx, &guild, author_vc).await {
+            respond(ctx, msg, Reply::OtherInstance).await;
+            return Ok(());
+        }
+        // already being in voice here doesn't take up another connection
+        if bot_vc.is_none() && at_capacity(ctx, &manager).await {
+            respond(ctx, msg, Reply::AtCapacity).await;
+            return Ok(());
+        }
+
+        // Discord ignores joins it won't allow instead of refusing them, so check for those first
+        if let Some(reply) = join_problem(ctx, &guild, author_vc).await {
+            respond(ctx, msg, reply).await;
+            return Ok(());
+        }
 
         // attempt to join voice channel
-        let (handle_lock, success) = manager.join(guild_id, author_vc).await;
+        let (_, success) = manager.join(guild.id, author_vc).await;
+
+        match success {
+            Ok(()) => {
+                if moving {
+                    queue_lock.lock().await.last_moved = Some(Instant::now());
+                }
+                respond(ctx, msg, Reply::Joined(&author_vc.mention().to_string())).await;
+            }
+            Err(why) => {
+                println!("Error joining voice channel: {:?}", why);
+                // songbird needs a leave before trying again after most failures
+                if why.should_leave_server() {
+                    let _ = manager.remove(guild.id).await;
+                }
 
-        if success.is_ok() {
-            msg.channel_id
-                .say(&ctx.http, &format!("Joined {}", author_vc.mention()))
-                .await?;
+                let reply = match why {
+                    JoinError::TimedOut => Reply::JoinTimedOut,
+                    JoinError::Driver(_) => Reply::VoiceServerFailed,
+                    _ => Reply::JoinFailed,
+                };
+                respond(ctx, msg, reply).await;
+            }
         }
     } else {
         must_be_in_vc(ctx, msg).await?;
@@ -56,401 +156,3519 @@ async fn summon(ctx: &Context, msg: &Message) -> CommandResult {
     Ok(())
 }
 
+// Why the bot can't join a voice channel, if it can tell beforehand
+async fn join_problem(ctx: &Context, guild: &Guild, channel_id: ChannelId) -> Option<Reply<'static>> {
+    let bot_id = ctx.cache.current_user_id().await;
+    if user_vc(guild, &bot_id) == Some(channel_id) {
+        return None;
+    }
+    let channel = guild.channels.get(&channel_id)?;
+    let bot = guild.members.get(&bot_id)?;
+    let permissions = guild.user_permissions_in(channel, bot).ok()?;
+
+    if !permissions.connect() {
+        return Some(Reply::CantConnect);
+    }
+    if !permissions.speak() {
+        return Some(Reply::CantSpeak);
+    }
+    // moving members also allows joining full channels
+    let users = guild.voice_states.values().filter(|state| state.channel_id == Some(channel_id)).count() as u64;
+    if channel.user_limit.is_some_and(|limit| limit > 0 && users >= limit) && !permissions.move_members() {
+        return Some(Reply::ChannelFull);
+    }
+    None
+}
+
+// Leaves and rejoins the voice channel to fix stuttering audio, the current song continues where it was
+#[command]
+#[only_in(guilds)]
+#[checks(DJ)]
+#[aliases("rejoin")]
+async fn reconnect(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let (manager, guild, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    let channel = match manager.get(guild.id) {
+        Some(call_lock) => call_lock.lock().await.current_channel(),
+        None => None,
+    };
+    let channel = match channel {
+        Some(channel) => ChannelId(channel.0),
+        None => {
+            respond(ctx, msg, Reply::NotInVc).await;
+            return Ok(());
+        }
+    };
+    // take the handle so the end event of the old track is ignored.
+    // The queue isn't held while reconnecting, leaving and joining lock the call
+    let handle = queue_lock.lock().await.now_playing.as_mut().and_then(|song| song.handle.take());
+    let mut info = None;
+    if let Some(handle) = handle {
+        info = handle.get_info().await.ok();
+        let _ = handle.stop();
+    }
+
+    let _ = manager.remove(guild.id).await;
+    let (call_lock, joined) = manager.join(guild.id, channel).await;
+    if let Err(why) = joined {
+        println!("Error rejoining voice: {:?}", why);
+        respond(ctx, msg, Reply::ReconnectFailed).await;
+        return Ok(());
+    }
+    respond(ctx, msg, Reply::Reconnected).await;
+
+    let call = call_lock.lock().await;
+    let mut queue = queue_lock.lock().await;
+    let eq = queue.eq;
+    if let (Some(song), Some(info)) = (&mut queue.now_playing, info) {
+        song.start = Some(info.position);
+
+        if !start_song(ctx, msg.channel_id, call_lock.clone(), Some(call), song, queue_lock.clone(), eq).await {
+            // carry over the repeats that were left
+            if let Some(handle) = &song.handle {
+                let _ = match info.loops {
+                    LoopState::Infinite => handle.enable_loop(),
+                    LoopState::Finite(count) if count > 0 => handle.loop_for(count),
+                    LoopState::Finite(_) => Ok(()),
+                };
+            }
+        }
+    }
+    Ok(())
+}
+
 async fn must_be_in_vc(ctx: &Context, msg: &Message) -> CommandResult {
-    msg.channel_id.say(ctx, "Must be in a voice channel to use this command").await?;
+    respond(ctx, msg, Reply::MustBeInVc).await;
     Ok(())
 }
 
 async fn nothing_playing(ctx: &Context, msg: &Message) -> CommandResult {
-    msg.channel_id.say(ctx, "Nothing playing").await?;
+    respond(ctx, msg, Reply::NothingPlaying).await;
     Ok(())
 }
 
-#[command]
-#[aliases("np", "nowplaying")]
-async fn now_playing(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+// Replies and returns false when the author isn't in the bot's voice channel, unless the server
+// turned the requirement off with the samechannel command. Passes when the bot isn't in voice.
+// Locks the call, so don't hold its lock while calling this.
+async fn require_same_channel(ctx: &Context, msg: &Message, guild: &Guild) -> bool {
     let manager = songbird::get(ctx)
         .await
-        .expect("Songbird Voice client passed in at initialization.")
-        .clone();
-    
-    let guild = msg.guild(&ctx.cache).await.unwrap();
-    let guild_id = guild.id;
-
-    if manager.get(guild_id).is_some() {
-        let queues = get_queues(ctx).await.clone();
-        let server_queue_lock = queues.queue_or_create(&guild_id).await.clone();
-        let server_queue = server_queue_lock.lock().await;
-        let avatar_url = ctx.http.get_current_user().await?.avatar_url();
-
-        if let Some(song) = &server_queue.now_playing {
-            let progress_bar = "▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬▬";
-            let current_position = "0:00";
-            let track_duration = format_duration(&song.duration);
-            msg.channel_id.send_message(ctx.http.clone(), |m| {
-                m.embed(|embed| {
-                    embed.author(|author| {
-                        author.name("Now Playing 🎵");
-
-                        if let Some(url) = avatar_url {
-                            author.icon_url(url);
-                        }
-                        author
-                    })
-                        .description(format!("{}\n\n`{}`\n\n`{} \\ {}`\n\n`Requested by:` {}", song.title_with_link(), progress_bar, current_position, track_duration, song.author));
+        .expect("Songbird Voice client passed in at initialization.");
+    let bot_channel = match manager.get(guild.id) {
+        Some(call_lock) => call_lock.lock().await.current_channel().map(|channel| ChannelId(channel.0)),
+        None => return true,
+    };
 
-                    if let SongSource::YouTube { id, url: _ } = &song.source {
-                        embed.thumbnail(format!("https://img.youtube.com/vi/{}/mqdefault.jpg", id));
-                    }
+    if bot_channel == user_vc(guild, &msg.author.id) || !get_settings(ctx).await.get(guild.id, |settings| settings.same_channel_only).await {
+        return true;
+    }
+    match bot_channel {
+        Some(channel) => respond(ctx, msg, Reply::ConnectedElsewhere(&channel.mention().to_string())).await,
+        None => respond(ctx, msg, Reply::NotSameVc).await,
+    }
+    false
+}
 
-                    embed
-                })
-            }).await?; 
-        } else {
-            nothing_playing(ctx, msg).await?;
+// Whether joining voice in another server would go over MAX_VOICE_CONNECTIONS
+async fn at_capacity(ctx: &Context, manager: &Songbird) -> bool {
+    let queues = get_queues(ctx).await;
+    let max = match queues.max_voice_connections {
+        Some(max) => max,
+        None => return false,
+    };
+    // every server the bot has joined has a queue
+    let guild_ids = queues.map.read().await.keys().copied().collect::<Vec<_>>();
+    let mut connected = 0;
+    for guild_id in guild_ids {
+        if let Some(call_lock) = manager.get(guild_id) {
+            // a call that's locked is busy with a command (ex. resolving a playlist), which only happens while connected.
+            // Waiting for it would stall this server behind lookups in that one
+            let in_channel = match call_lock.try_lock() {
+                Ok(call) => call.current_channel().is_some(),
+                Err(_) => true,
+            };
+            if in_channel {
+                connected += 1;
+            }
         }
-    } else {
-        nothing_playing(ctx, msg).await?;
     }
-
-    Ok(())
+    connected >= max
 }
 
-fn format_duration(duration: &Duration) -> String {
-    let secs = duration.as_secs();
-    let mins = secs / 60;
-    let hours = mins / 60;
-
-    if hours != 0 {
-        format!("{:02}:{:02}:{:02}", hours, mins % 60, secs % 60)
-    } else {
-        format!("{:02}:{:02}", mins % 60, secs % 60)
+// Looks up the songbird manager, guild and queue that nearly every command needs.
+// Replies and returns None when used outside of a server or when the server isn't cached.
+async fn resolve_context(ctx: &Context, msg: &Message) -> Option<(Arc<Songbird>, Guild, Arc<Mutex<ServerQueue>>)> {
+    if msg.guild_id.is_none() {
+        respond(ctx, msg, Reply::ServerOnly).await;
+        return None;
     }
-}
 
-#[command]
-#[aliases("p")]
-async fn play(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-    let message = args.message();
-    
-    let guild = msg.guild(&ctx.cache).await.unwrap();
-    let guild_id = guild.id;
-    
+    // the cache can be cold right after startup
+    let guild = match msg.guild(&ctx.cache).await {
+        Some(guild) => guild,
+        None => {
+            respond(ctx, msg, Reply::ServerNotCached).await;
+            return None;
+        }
+    };
     let manager = songbird::get(ctx)
         .await
         .expect("Songbird Voice client passed in at initialization.")
         .clone();
+    let queue_lock = get_queues(ctx)
+        .await
+        .queue_or_create(&guild.id)
+        .await;
 
-    // Retrieve call ref or obtain one by joining the call
-    let mut call_lock = manager.get(guild_id);
-    
-    if call_lock.is_none() {
-        if let Some(author_vc) = user_vc(&guild, &msg.author.id) {
-            let (handle_lock, _) = manager.join(guild_id, author_vc).await;
-            call_lock = Some(handle_lock);
-        } else {
-            must_be_in_vc(ctx, msg).await?;
-        }
-    }
-        
-    // Should only be None if the sender is not in vc
-    if let Some(call_lock) = call_lock {
-        let call = call_lock.lock().await;
-
-        // Check if user is in same channel as bot
-        if call.current_channel().unwrap().0 != user_vc(&guild, &msg.author.id).map_or(0, |val| val.0) {
-            check_msg(msg.channel_id.say(&ctx.http, "You must be in the same voice channel to use this command.").await)
-        }
-
-        // Searches the song
-        if let Some(mut song) = get_song(ctx, msg, message).await {
-            // get server's track queue
-            // clones are necessary to avoid thread deadlock (arcs must stay within their own threads)
-            let queues = get_queues(ctx).await.clone();
-            let server_queue_lock = queues.queue_or_create(&guild_id).await.clone();
-            let mut server_queue = server_queue_lock.lock().await;
-
-            if server_queue.now_playing.is_some() {
-                let avatar_url = ctx.http.get_current_user().await?.avatar_url();
-                let linked_title = &song.title_with_link().clone();
-                let artist = &song.artist.clone();
-                let track_duration = format_duration(&song.duration);
-    
-                check_msg(msg.channel_id.send_message(&ctx.http, |m| {
-                    m.embed(|e| {
-                        e.author(|a| {
-                            a.name("Added to queue");
-                            
-                            if let Some(avatar_url) = avatar_url {
-                                a.icon_url(avatar_url);
-                            }
-                            a
-                        })
-                            .description(format!("**{}**", linked_title))
-                            .field("Channel", format!("{}", artist), true)
-                            .field("Song Duration", format!("{}", track_duration), true)
-                            .field("Time until playing", "todo", true)
-                            .field("Position in queue", server_queue.queue.len() + 1, false);
-                        if let SongSource::YouTube { id, url: _ } = &song.source {
-                            e.thumbnail(format!("https://img.youtube.com/vi/{}/mqdefault.jpg", id));
-                        }
-                        e
-                    })
-                }).await);
-    
-                server_queue.queue.push_back(song);
-            } else {
-                if play_song(ctx.http.clone(), msg.channel_id, call_lock.clone(), Some(call), &mut song, server_queue_lock.clone()).await {
-                    return Ok(());
-                }
+    Some((manager, guild, queue_lock))
+}
 
-                // move at the very end
-                server_queue.now_playing = Some(song);
-            }
-        } else {
-            check_msg(msg.channel_id.say(&ctx.http, "No matches").await);
+// Same as resolve_context, but also replies and returns None when the bot isn't in a voice channel
+async fn resolve_call(ctx: &Context, msg: &Message) -> Option<(Arc<Mutex<Call>>, Guild, Arc<Mutex<ServerQueue>>)> {
+    let (manager, guild, queue_lock) = resolve_context(ctx, msg).await?;
+
+    match manager.get(guild.id) {
+        Some(call_lock) => Some((call_lock, guild, queue_lock)),
+        None => {
+            respond(ctx, msg, Reply::NotInVc).await;
+            None
         }
-    } else {
-        check_msg(msg.channel_id.say(&ctx.http, "Cannot play this type of link").await);
     }
-
-    Ok(())
 }
 
-// Obtains a lock from call_lock, make sure locks are not held earlier in the call stack
-async fn play_song(http: Arc<Http>, text_channel: ChannelId, call_lock: Arc<Mutex<Call>>, call: Option<MutexGuard<'_, Call>>, song: &mut Song, server_queue: Arc<Mutex<ServerQueue>>) -> bool {
-    let source = match song.source.as_input().await {
-        Ok(source) => source,
-        Err(why) => {
-            println!("Err starting source: {:?}", why);
-            check_msg(text_channel.say(http, "Error sourcing ffmpeg").await);
-
-            return true;
+// Pauses while nobody is listening and resumes once someone joins the channel again
+pub(crate) async fn pause_when_alone(ctx: &Context, guild_id: GuildId) {
+    let manager = songbird::get(ctx)
+        .await
+        .expect("Songbird Voice client passed in at initialization.")
+        .clone();
+    let bot_channel = match manager.get(guild_id) {
+        Some(call_lock) => match call_lock.lock().await.current_channel() {
+            Some(channel) => ChannelId(channel.0),
+            None => return,
         },
+        None => return,
     };
 
-    // cannot use .unwrap_or because locking val must be lazy
-    // cannot use .unwrap_or_else because closure cannot be async
-    let mut call = match call  {
-        Some(call) => call,
-        None => call_lock.lock().await,
+    let bot_id = ctx.cache.current_user_id().await;
+    let listeners = ctx.cache.guild_field(guild_id, |guild| {
+        guild.voice_states
+            .values()
+            .filter(|state| state.channel_id == Some(bot_channel) && state.user_id != bot_id)
+            .filter(|state| !state.member.as_ref().is_some_and(|member| member.user.bot))
+            .count()
+    }).await;
+    let listeners = match listeners {
+        Some(listeners) => listeners,
+        None => return,
     };
-    let track = call.play_source(source);
-    let send_http = http.clone();
-    let send_call_lock = Arc::downgrade(&call_lock.clone());
 
-    // song ends
-    let _ = track.add_event(
-        Event::Track(TrackEvent::End),
-        SongEndNotifier {
-            text_channel: text_channel.clone(),
-            http: send_http,
-            server_queue: server_queue.clone(),
-            call_lock: send_call_lock,
-        },
-    );
+    let queue_lock = get_queues(ctx).await.queue_or_create(&guild_id).await;
+    let mut queue = queue_lock.lock().await;
+    let handle = match queue.now_playing.as_ref().and_then(|song| song.handle.clone()) {
+        Some(handle) => handle,
+        None => return,
+    };
 
-    // move track into song
-    song.handle.replace(track);
+    if listeners == 0 && !queue.paused_alone {
+        if handle.pause().is_ok() {
+            queue.paused_alone = true;
+            queue.idle_since.get_or_insert_with(Instant::now);
+        }
+    } else if listeners > 0 && queue.paused_alone {
+        // only resume what was paused here
+        let _ = handle.play();
+        queue.paused_alone = false;
+        queue.idle_since = None;
+    }
+}
 
-    check_msg(text_channel.say(http, format!("**Playing** 🎶 `{}` - Now!", song.title)).await);
+// Leaves voice channels where the queue ran out or playback stayed paused for the timeout.
+// Runs for as long as the bot does.
+pub(crate) async fn disconnect_idle(ctx: Context) {
+    let manager = songbird::get(&ctx)
+        .await
+        .expect("Songbird Voice client passed in at initialization.")
+        .clone();
+    let settings = get_settings(&ctx).await;
+    let mut interval = tokio::time::interval(Duration::from_secs(30));
 
-    false
-}
+    loop {
+        interval.tick().await;
 
-struct SongEndNotifier {
-    // sending message
-    text_channel: ChannelId,
-    http: Arc<Http>,
-    // shifting queue
-    server_queue: Arc<Mutex<ServerQueue>>,
-    // playing song
-    call_lock: Weak<Mutex<Call>>,
-}
+        let queues = get_queues(&ctx).await.map.read().await.values().cloned().collect::<Vec<_>>();
+        let mut idle = Vec::new();
+        for queue_lock in queues {
+            let mut queue = queue_lock.lock().await;
 
-#[async_trait]
-impl VoiceEventHandler for SongEndNotifier {
-    async fn act(&self, _: &EventContext<'_>) -> Option<Event> {
-        if let Some(call_lock) = self.call_lock.upgrade() {
-            let mut queue = self.server_queue.lock().await;
-        
-            queue.shift_queue();
-            if let Some(now_playing) = &mut queue.now_playing {
-                play_song(self.http.clone(), self.text_channel, call_lock, None, now_playing, self.server_queue.clone()).await;
+            let timeout = match settings.idle_timeout(queue.guild_id).await {
+                Some(timeout) if manager.get(queue.guild_id).is_some() => timeout,
+                _ => {
+                    queue.idle_since = None;
+                    continue;
+                }
+            };
+
+            let paused = match queue.now_playing.as_ref().and_then(|song| song.handle.as_ref()) {
+                Some(handle) => handle.get_info().await.is_ok_and(|info| info.playing == PlayMode::Pause),
+                None => true,
+            };
+            if !paused {
+                queue.idle_since = None;
+                continue;
+            }
+
+            let idle_since = *queue.idle_since.get_or_insert_with(Instant::now);
+            if idle_since.elapsed() >= timeout {
+                queue.idle_since = None;
+                idle.push((queue.guild_id, queue_lock.clone()));
             }
         }
 
-        //check_msg(self.text_channel.say(&self.http, "Song ended!").await);
-        None
+        // leaving locks the call, so the queue is let go of first. play locks the call before the queue
+        for (guild_id, queue_lock) in idle {
+            if let Err(why) = manager.remove(guild_id).await {
+                println!("Error leaving idle voice channel: {:?}", why);
+            }
+            let mut queue = queue_lock.lock().await;
+            queue.stop();
+            update_live_queue(&ctx, &queue).await;
+        }
     }
 }
 
-#[command]
-#[aliases("q")]
-async fn queue(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
-    let manager = songbird::get(ctx)
+// Rejoins and continues what every server was playing when the bot last stopped, then keeps
+// saving the queues so the next start can do the same
+pub(crate) async fn resume_queues(ctx: Context, path: PathBuf) {
+    let manager = songbird::get(&ctx)
         .await
-        .expect("Songbird not yet initialized")
+        .expect("Songbird Voice client passed in at initialization.")
         .clone();
- 
-    let guild = msg.guild(&ctx.cache).await.unwrap();
-    let guild_id = guild.id;
-
-    if !manager.get(guild_id).is_none() {
-        let queues = get_queues(ctx).await.clone();
-        let server_queue_lock = queues.queue_or_create(&guild_id).await.clone();
-        let server_queue = server_queue_lock.lock().await;
-
-        let mut description = "__Now Playing:__\n".to_owned();
-        if let Some(now_playing) = &server_queue.now_playing {
-            description.push_str(&format!("{} | `{} Requested by: {}`", now_playing.title_with_link(), format_duration(&now_playing.duration), now_playing.author))
-        } else {
-            description.push_str("Nothing");
-        }
-        if !server_queue.queue.is_empty() {
-            description.push_str("\n\n__Up Next:__\n");
-            for (i, song) in server_queue.queue.iter().enumerate().filter(|(i , _)| *i < 10) {
-                description.push_str(&format!("`{}.` {} | `{} Requested by: {}`", i + 1, song.title_with_link(), format_duration(&song.duration), song.author));
-                
-                if i + 1 < server_queue.queue.len() {
-                    description.push_str("\n\n");
-                }
-            }
-        }
 
-        msg.channel_id.send_message(ctx.http.clone(), |m| {
-            m.embed(|e| {
-                e.title(format!("Queue for {}", guild.name))
-                    .description(description)
-            })
-        }).await?;
+    for (guild_id, saved) in resume::load(&path) {
+        resume_queue(&ctx, &manager, GuildId(guild_id), saved).await;
     }
 
-    Ok(())
-}
+    let mut interval = tokio::time::interval(RESUME_SAVE_INTERVAL);
+    loop {
+        interval.tick().await;
 
-#[command]
-#[only_in(guilds)]
-#[aliases("s", "fs")]
-async fn skip(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
-    let guild = msg.guild(&ctx.cache).await.unwrap();
-    let guild_id = guild.id;
+        let mut saved = HashMap::new();
+        let queues = get_queues(&ctx).await.map.read().await.iter().map(|(guild_id, queue)| (*guild_id, queue.clone())).collect::<Vec<_>>();
+        for (guild_id, queue_lock) in queues {
+            // the call is locked before the queue, like play does, or the two could wait on each other
+            let voice_channel = match manager.get(guild_id) {
+                Some(call_lock) => call_lock.lock().await.current_channel(),
+                None => None,
+            };
+            let queue = queue_lock.lock().await;
+            let (voice_channel, text_channel, song) = match (voice_channel, queue.text_channel, &queue.now_playing) {
+                (Some(voice_channel), Some(text_channel), Some(song)) => (voice_channel, text_channel, song),
+                _ => continue,
+            };
+            let position = match (&song.source, &song.handle) {
+                // attachments aren't saved, so the position would belong to the next song
+                (SongSource::Direct { .. }, _) => Duration::ZERO,
+                (_, Some(handle)) => handle.get_info().await.map_or(Duration::ZERO, |info| info.position),
+                (_, None) => Duration::ZERO,
+            };
 
-    let manager = songbird::get(ctx)
-        .await
-        .expect("Songbird not yet initialized")
-        .clone();
+            saved.insert(queue.guild_id.0, SavedQueue {
+                voice_channel: voice_channel.0,
+                text_channel: text_channel.0,
+                position,
+                songs: std::iter::once(song).chain(queue.queue.iter()).filter_map(SavedSong::from_song).collect(),
+            });
+        }
+        resume::save(&path, &saved).await;
+    }
+}
 
-    if let Some(handler_lock) = manager.get(guild_id) {
-        let mut handler = handler_lock.lock().await;
-        let _ = handler.stop();
-        let queue_lock = get_queues(ctx)
-            .await
-            .queue_or_create(&guild_id)
-            .await;
-        let mut queue = queue_lock.lock().await;
-        if let Some(now_playing) = &queue.now_playing {
-            now_playing.handle.as_ref().unwrap().send(songbird::tracks::TrackCommand::Stop)?;
-    
-            check_msg(msg.channel_id.say(&ctx.http, "Skipped!").await);
+// How often the playback position is saved, at most this much is replayed after a restart
+const RESUME_SAVE_INTERVAL: Duration = Duration::from_secs(15);
+
+async fn resume_queue(ctx: &Context, manager: &Songbird, guild_id: GuildId, saved: SavedQueue) {
+    let mut songs = saved.songs.into_iter().map(SavedSong::into_song).collect::<VecDeque<_>>();
+    let mut song = match songs.pop_front() {
+        Some(song) => song,
+        None => return,
+    };
+    // livestreams can't be seeked, and a song that was nearly over just moves on to the next one
+    if !song.live {
+        // the position is checked against the source as it is now, it can have changed since it was saved
+        if let Some(duration) = song.source.current_duration(&*get_ytdl_config(ctx).await).await {
+            song.duration = duration;
+        }
+        if song.duration.is_zero() || saved.position < song.duration {
+            song.start = Some(saved.position).filter(|position| !position.is_zero());
         } else {
-            nothing_playing(ctx, msg).await?;
+            song = match songs.pop_front() {
+                Some(song) => song,
+                None => return,
+            };
         }
-    } else {
-        check_msg(msg.channel_id.say(&ctx.http, "Not in a voice channel").await);
     }
-    Ok(())
-}
 
-#[hook]
-pub(crate) async fn after(_: &Context, _: &Message, command_name: &str, command_result: CommandResult) {
-    match command_result {
-        Err(why) => println!(
-            "Command '{}' returned error {:?} => {}",
-            command_name, why, why
-        ),
-        _ => (),
+    let (call_lock, joined) = manager.join(guild_id, ChannelId(saved.voice_channel)).await;
+    if let Err(why) = joined {
+        println!("Error rejoining voice to resume: {:?}", why);
+        let _ = manager.remove(guild_id).await;
+        return;
     }
-}
 
-#[help]
-#[command_not_found_text = "Could not find: `{}`."]
-#[max_levenshtein_distance(3)]
-#[individual_command_tip=""]
-#[strikethrough_commands_tip_in_guild=""]
-async fn my_help(
-    ctx: &Context,
-    msg: &Message,
-    args: Args,
-    help_options: &'static HelpOptions,
-    groups: &[&'static CommandGroup],
-    owners: HashSet<UserId>,
-) -> CommandResult {
-    let _ =  help_commands::with_embeds(ctx, msg, args, help_options, groups, owners).await;
-    Ok(())
-}
+    let queue_lock = get_queues(ctx).await.queue_or_create(&guild_id).await;
+    let call = call_lock.lock().await;
+    let mut queue = queue_lock.lock().await;
+    let text_channel = ChannelId(saved.text_channel);
+    queue.text_channel = Some(text_channel);
+    for song in songs {
+        queue.push_back(song);
+    }
 
-fn check_msg(result: Result<Message>) {
-    if let Err(why) = result {
-        println!("Error sending message: {:?}", why);
+    let eq = queue.eq;
+    if !play_song(ctx, text_channel, call_lock.clone(), Some(call), &mut song, queue_lock.clone(), eq).await {
+        queue.now_playing = Some(song);
     }
+    update_live_queue(ctx, &queue).await;
 }
 
-async fn get_song(ctx: &Context, msg: &Message, message: &str) -> Option<Song> {
-    if message.starts_with("http") {
-        if message.contains("spotify.com/track/") {
-            // Spotify link
-            let api_access = get_api_access(ctx).await.clone();
-            let track_id = &message.split("track/").nth(1).unwrap()[ .. 22];
-            let track = api_access.get_spotify_track(track_id).await;
-            let video = first_yt_result(ctx, &format!("{} {} lyrics explicit", track.name, track.artists.get(0).map_or("", |artist| &artist.name))).await;
-
-            Some(video.as_song(msg.author.tag()))
-        } else if message.contains("soundcloud") {
-            // Soundcloud link
-            None
-        } else {
-            // YouTube Link
-            let link = message.to_owned();
-            let id = link.split("?v=").nth(1);
+// Cycles the bot's activity through the STATUS_MESSAGES. {prefix} and {servers} are filled in,
+// and messages with {song} show something playing in any server and are skipped when nothing is.
+pub(crate) async fn rotate_status(ctx: Context, statuses: Vec<String>, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    let count = statuses.len();
+    let mut statuses = statuses.iter().cycle();
 
-            if let Some(id) = id {
-                if id.len() < 11 {
-                    return None;
-                }
+    loop {
+        interval.tick().await;
 
-                let id = &id[ .. 11 ];
-                let api_access = get_api_access(ctx).await.clone();
-                let track = api_access.get_video_info(id).await;
+        let prefix = get_settings(&ctx).await.prefix(None).await;
+        let servers = ctx.cache.guild_count().await;
+        let queues = get_queues(&ctx).await.map.read().await.values().cloned().collect::<Vec<_>>();
+        let mut song = None;
+        for queue_lock in queues {
+            if let Some(now_playing) = &queue_lock.lock().await.now_playing {
+                song = Some(now_playing.short_title());
+                break;
+            }
+        }
 
-                return Some(track.as_song(msg.author.tag()));
+        // at most one pass over the list, so every message needing a song doesn't spin forever
+        for status in statuses.by_ref().take(count) {
+            if status.contains("{song}") && song.is_none() {
+                continue;
             }
+            let text = status
+                .replace("{prefix}", &prefix)
+                .replace("{servers}", &servers.to_string())
+                .replace("{song}", song.as_deref().unwrap_or_default());
+            ctx.set_activity(Activity::playing(text)).await;
+            break;
+        }
+    }
+}
 
-            None
+#[command]
+#[aliases("np", "nowplaying")]
+async fn now_playing(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let (manager, guild, server_queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    // the queue outlives the voice connection, so still show what was playing after a disconnect
+    let connected = manager.get(guild.id).is_some();
+
+    let server_queue = server_queue_lock.lock().await;
+    if let Some(song) = &server_queue.now_playing {
+        let message = send_now_playing(ctx, msg.channel_id, &server_queue, connected).await?;
+
+        if server_queue.live_progress && !song.live {
+            tokio::spawn(update_progress(ctx.clone(), message, server_queue_lock.clone(), song.id));
         }
     } else {
-        let result = first_yt_result(ctx, message).await;
-        Some(result.as_song(msg.author.tag()))
+        nothing_playing(ctx, msg).await?;
     }
+
+    Ok(())
 }
 
-async fn first_yt_result(ctx: &Context, query: &str) -> YouTubeVideo {
-    let api_access = get_api_access(ctx).await.clone();
-    let results = api_access.search_yt(query).await;
-    let first = &results.items[0];
-    let id = &first.id.video_id;
-    let duration = api_access.get_video_duration(id).await;
+// Sends the now playing embed, the queue must have a song playing
+async fn send_now_playing(ctx: &Context, channel_id: ChannelId, server_queue: &ServerQueue, connected: bool) -> Result<Message> {
+    let embed = now_playing_embed(ctx, server_queue, connected).await;
+
+    send_embed(ctx, channel_id, embed, None).await
+}
+
+// Edits a now playing message until the song it shows ends or live progress is turned off
+async fn update_progress(ctx: Context, message: Message, queue_lock: Arc<Mutex<ServerQueue>>, song_id: u64) {
+    let mut interval = tokio::time::interval(PROGRESS_INTERVAL);
+    // the first tick is immediate and the message is already up to date
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
 
-    YouTubeVideo {
-        name: first.snippet.title.clone(),
-        channel: first.snippet.channel_title.clone(),
-        duration,
-        id: id.clone(),
+        let queue = queue_lock.lock().await;
+        if !queue.live_progress || queue.now_playing.as_ref().is_none_or(|song| song.id != song_id) {
+            return;
+        }
+        let connected = songbird::get(&ctx)
+            .await
+            .expect("Songbird Voice client passed in at initialization.")
+            .get(queue.guild_id)
+            .is_some();
+        let embed = now_playing_embed(&ctx, &queue, connected).await;
+        drop(queue);
+
+        // stop once the message is deleted
+        if edit_embed(&ctx, message.channel_id, message.id, embed).await.is_err() {
+            return;
+        }
     }
 }
 
-async fn get_api_access(ctx: &Context) -> Arc<ApiAccess> {
-    ctx.data.read().await.get::<ApiAccessKey>().cloned().expect("API Access not yet initialized")
+// Each edit counts against the channel's rate limit, so don't go much faster than this
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(15);
+
+// ex. "▬▬▬▬🔘▬▬▬▬▬" for a song a little under halfway through
+fn progress_bar(position: Duration, duration: Duration) -> String {
+    const LENGTH: usize = 30;
+    let filled = if duration.is_zero() {
+        0
+    } else {
+        ((position.as_secs_f64() / duration.as_secs_f64() * LENGTH as f64) as usize).min(LENGTH - 1)
+    };
+
+    format!("{}🔘{}", "▬".repeat(filled), "▬".repeat(LENGTH - 1 - filled))
+}
+
+// The queue must have a song playing
+async fn now_playing_embed(ctx: &Context, server_queue: &ServerQueue, connected: bool) -> CreateEmbed {
+    let song = server_queue.now_playing.as_ref().unwrap();
+    let avatar_url = ctx.cache.current_user().await.avatar_url();
+
+    // the handle knows the position, which follows seeks and start offsets, and how many repeats are left
+    let info = match &song.handle {
+        Some(handle) => handle.get_info().await.ok(),
+        None => None,
+    };
+
+    // livestreams have no end, so show who's watching instead of progress
+    let progress = if song.live {
+        let viewers = match song.source.youtube_id() {
+            Some(id) => get_search_provider(ctx).await.live_viewers(id).await,
+            None => None,
+        };
+        match viewers {
+            Some(viewers) => format!("🔴 LIVE · {} watching", viewers),
+            None => "🔴 LIVE".to_owned(),
+        }
+    } else {
+        let position = info.as_ref().map_or(Duration::ZERO, |info| info.position);
+        format!("`{}`\n\n`{} \\ {}`", progress_bar(position, song.duration), format_duration(&position), format_duration(&song.duration))
+    };
+
+    let chapter = match info.as_ref().and_then(|info| song.chapter_at(info.position)) {
+        Some(chapter) => format!("\n\n`Chapter:` {}", truncate(&chapter.title, MAX_TITLE_LENGTH)),
+        None => String::new(),
+    };
+
+    let mut loop_status = String::new();
+    if server_queue.looping.is_some() {
+        match info.as_ref().map(|info| info.loops) {
+            Some(LoopState::Infinite) => loop_status.push_str("\n\n🔂 Looping"),
+            Some(LoopState::Finite(1)) => loop_status.push_str("\n\n🔂 1 repeat left"),
+            Some(LoopState::Finite(n)) if n > 0 => loop_status.push_str(&format!("\n\n🔂 {} repeats left", n)),
+            _ => {}
+        }
+    }
+
+    let disconnected_note = if connected { "" } else { "\n\n⚠️ Playback is disconnected" };
+    let up_next = match server_queue.queue.front() {
+        Some(next) => format!("\n\n`Up next:` {}", next.short_title()),
+        None if server_queue.autoplay => "\n\n`Up next:` autoplay".to_owned(),
+        None => String::new(),
+    };
+
+    let mut embed = CreateEmbed::default();
+    embed.author(|author| {
+        author.name("Now Playing 🎵");
+
+        if let Some(url) = &avatar_url {
+            author.icon_url(url);
+        }
+        author
+    })
+        .description(format!("{}{}\n\n{}\n\n`Requested by:` {}{}{}{}", song.title_with_link(), chapter, progress, song.author, up_next, loop_status, disconnected_note));
+
+    // fall back to the bot's avatar for sources without artwork
+    if let Some(url) = song.thumbnail_url.as_ref().or(avatar_url.as_ref()) {
+        embed.thumbnail(url);
+    }
+
+    embed
+}
+
+fn format_duration(duration: &Duration) -> String {
+    let secs = duration.as_secs();
+    let mins = secs / 60;
+    let hours = mins / 60;
+
+    if hours != 0 {
+        format!("{:02}:{:02}:{:02}", hours, mins % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", mins % 60, secs % 60)
+    }
+}
+
+// Parses the "t" parameter of a YouTube link, ex. "75", "75s" or "1m15s", or a time like "1:15"
+fn parse_timestamp(timestamp: &str) -> Option<Duration> {
+    if timestamp.contains(':') {
+        let mut secs = 0;
+        for part in timestamp.split(':') {
+            secs = secs * 60 + part.parse::<u64>().ok()?;
+        }
+        return Some(Duration::from_secs(secs));
+    }
+
+    let mut secs = 0;
+    let mut number = String::new();
+
+    for c in timestamp.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+        } else {
+            let value = number.parse::<u64>().ok()?;
+            number.clear();
+
+            secs += match c {
+                'h' => value * 3600,
+                'm' => value * 60,
+                's' => value,
+                _ => return None,
+            };
+        }
+    }
+    if !number.is_empty() {
+        secs += number.parse::<u64>().ok()?;
+    }
+
+    Some(Duration::from_secs(secs))
+}
+
+#[command]
+#[aliases("p")]
+#[bucket = "youtube"]
+async fn play(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    record_search(ctx, msg.author.id, args.message()).await;
+    play_query(ctx, msg, args.message(), false).await
+}
+
+// How many searches are remembered per user
+const SEARCH_HISTORY_SIZE: usize = 10;
+
+pub(crate) struct SearchHistoryKey;
+impl TypeMapKey for SearchHistoryKey {
+    type Value = Arc<Mutex<HashMap<UserId, VecDeque<String>>>>;
+}
+
+// Remembers what a user searched for, most recent first. Searching for something again moves it to the top
+async fn record_search(ctx: &Context, user_id: UserId, query: &str) {
+    let query = query.trim();
+    if query.is_empty() {
+        return;
+    }
+    let history = get_search_history(ctx).await;
+    let mut history = history.lock().await;
+    let searches = history.entry(user_id).or_default();
+
+    searches.retain(|search| search != query);
+    searches.push_front(query.to_owned());
+    searches.truncate(SEARCH_HISTORY_SIZE);
+}
+
+// Lists your recent searches, or plays one of them again by its number
+#[command]
+#[only_in(guilds)]
+#[aliases("searches")]
+#[bucket = "youtube"]
+async fn history(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let searches = get_search_history(ctx).await.lock().await.get(&msg.author.id).cloned().unwrap_or_default();
+    if searches.is_empty() {
+        respond(ctx, msg, Reply::NoSearchHistory).await;
+        return Ok(());
+    }
+
+    if args.is_empty() {
+        let lines = searches
+            .iter()
+            .enumerate()
+            .map(|(i, query)| format!("`{}.` {}", i + 1, truncate(query, 100)))
+            .collect::<Vec<_>>();
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("Recent searches by {}", msg.author.name))
+            .description(lines.join("\n"))
+            .footer(|f| f.text("Use history <number> to play one again"));
+        send_embed(ctx, msg.channel_id, embed, None).await?;
+        return Ok(());
+    }
+
+    match args.single::<usize>().ok().and_then(|index| searches.get(index.wrapping_sub(1))) {
+        Some(query) => {
+            record_search(ctx, msg.author.id, query).await;
+            play_query(ctx, msg, query, false).await
+        }
+        None => {
+            respond(ctx, msg, Reply::SearchHistoryUsage).await;
+            Ok(())
+        }
+    }
+}
+
+const SEARCH_CHOICES: usize = 5;
+
+// Lists the top search results and plays the one picked by replying with its number.
+// Replying "<number> next" plays it next instead, and "all" queues every result.
+#[command]
+#[only_in(guilds)]
+#[bucket = "youtube"]
+async fn search(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let query = args.message().trim();
+    if query.is_empty() {
+        respond(ctx, msg, Reply::SearchUsage).await;
+        return Ok(());
+    }
+    record_search(ctx, msg.author.id, query).await;
+
+    let results = match get_search_provider(ctx).await.search(query).await {
+        Ok(results) => results.into_iter().take(SEARCH_CHOICES).collect::<Vec<_>>(),
+        Err(QuotaExceeded) => {
+            respond(ctx, msg, Reply::QuotaExceeded).await;
+            return Ok(());
+        }
+    };
+    if results.is_empty() {
+        respond(ctx, msg, Reply::NoMatches).await;
+        return Ok(());
+    }
+
+    let timeout = get_settings(ctx).await.get(msg.guild_id.unwrap(), |settings| settings.selection_timeout).await;
+    let choices = results
+        .iter()
+        .enumerate()
+        .map(|(i, song)| format!("`{}.` {} | `{}`", i + 1, song.title_with_link(), format_duration(&song.duration)))
+        .collect::<Vec<_>>();
+    let mut embed = CreateEmbed::default();
+    embed.title(format!("Results for \"{}\"", truncate(query, 100)))
+        .description(choices.join("\n"))
+        .footer(|f| f.text(format!("Reply with a number, \"<number> next\" to play it next, or \"all\" within {} seconds", timeout)));
+    send_embed(ctx, msg.channel_id, embed, None).await?;
+
+    let reply = msg.channel_id
+        .await_reply(&ctx)
+        .author_id(msg.author.id)
+        .timeout(Duration::from_secs(timeout))
+        .await;
+    let reply = match reply {
+        Some(reply) => reply,
+        None => {
+            respond(ctx, msg, Reply::SelectionTimedOut).await;
+            return Ok(());
+        }
+    };
+
+    let content = reply.content.trim().to_lowercase();
+    let (choice, front) = match content.strip_suffix("next") {
+        Some(choice) => (choice.trim(), true),
+        None => (content.as_str(), false),
+    };
+    // same as queuefront, which is limited to DJs
+    if front && !is_dj(ctx, msg).await {
+        respond(ctx, msg, Reply::NeedDj).await;
+        return Ok(());
+    }
+
+    let urls = if choice == "all" {
+        results.iter().map(|song| song.source.url()).collect::<Vec<_>>()
+    } else {
+        match choice.parse::<usize>().ok().and_then(|choice| results.get(choice.wrapping_sub(1))) {
+            Some(song) => vec![song.source.url()],
+            None => {
+                respond(ctx, msg, Reply::SelectionCancelled).await;
+                return Ok(());
+            }
+        }
+    };
+    // one link per line queues them together, like pasting a list into play
+    play_query(ctx, msg, &urls.join("\n"), front).await
+}
+
+// Plays a song next, or right away if nothing is playing
+#[command]
+#[only_in(guilds)]
+#[checks(DJ)]
+#[aliases("playtop", "pt")]
+#[bucket = "youtube"]
+async fn queuefront(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    play_query(ctx, msg, args.message(), true).await
+}
+
+async fn play_query(ctx: &Context, msg: &Message, message: &str, front: bool) -> CommandResult {
+    let (manager, guild, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+
+    // a bare play in reply to a message plays the links in it, or searches for what it says
+    let replied_to = match &msg.referenced_message {
+        Some(referenced) if message.trim().is_empty() && msg.attachments.is_empty() => {
+            let links = links_in(&referenced.content);
+            if links.is_empty() { referenced.content.trim().to_owned() } else { links.join("\n") }
+        }
+        _ => String::new(),
+    };
+    let message = if replied_to.is_empty() { message } else { replied_to.as_str() };
+
+    // a bare play resumes paused playback instead of searching for nothing
+    if message.trim().is_empty() && msg.attachments.is_empty() {
+        let handle = queue_lock.lock().await.now_playing.as_ref().and_then(|song| song.handle.clone());
+        let paused = match &handle {
+            Some(handle) => handle.get_info().await.is_ok_and(|info| info.playing == PlayMode::Pause),
+            None => false,
+        };
+
+        match handle {
+            Some(handle) if paused => {
+                if require_same_channel(ctx, msg, &guild).await {
+                    handle.play()?;
+                    let mut queue = queue_lock.lock().await;
+                    queue.paused_alone = false;
+                    queue.idle_since = None;
+                    respond(ctx, msg, Reply::Resumed).await;
+                }
+            }
+            _ => respond(ctx, msg, Reply::PlayUsage).await,
+        }
+        return Ok(());
+    }
+
+    // Retrieve call ref or obtain one by joining the call
+    let call_lock = match manager.get(guild.id) {
+        Some(call_lock) => call_lock,
+        None => match user_vc(&guild, &msg.author.id) {
+            Some(_) if !get_settings(ctx).await.auto_join(guild.id).await => {
+                respond(ctx, msg, Reply::SummonFirst).await;
+                return Ok(());
+            }
+            Some(author_vc) if other_instance_in(ctx, &guild, author_vc).await => {
+                respond(ctx, msg, Reply::OtherInstance).await;
+                return Ok(());
+            }
+            Some(_) if at_capacity(ctx, &manager).await => {
+                respond(ctx, msg, Reply::AtCapacity).await;
+                return Ok(());
+            }
+            Some(author_vc) => manager.join(guild.id, author_vc).await.0,
+            None => return must_be_in_vc(ctx, msg).await,
+        },
+    };
+
+    // stop here so nothing gets queued from outside the bot's channel
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+    queue_lock.lock().await.text_channel = Some(msg.channel_id);
+    let call = call_lock.lock().await;
+
+    // a pasted list of songs is resolved one query at a time and queued together
+    let mut queries = split_queries(message);
+    if queries.len() > 1 && msg.attachments.is_empty() {
+        // each line is its own search, so a long paste is cut off like a long playlist
+        let max = get_api_access(ctx).await.max_playlist_size;
+        let dropped = queries.len().saturating_sub(max);
+        queries.truncate(max);
+
+        let mut songs = Vec::new();
+        let mut failed = 0;
+        let mut quota_exceeded = false;
+
+        for query in &queries {
+            match get_songs(ctx, msg, query).await {
+                Ok(batch) if !batch.songs.is_empty() => songs.extend(batch.songs),
+                Ok(_) => failed += 1,
+                Err(LookupError::QuotaExceeded) => {
+                    failed += 1;
+                    quota_exceeded = true;
+                }
+                // counted with the other failures instead of a reply per line
+                Err(_) => failed += 1,
+            }
+        }
+
+        if !songs.is_empty() {
+            enqueue_or_play(ctx, msg, call_lock.clone(), Some(call), queue_lock, songs.into(), front).await?;
+        }
+        respond(ctx, msg, Reply::QueriesAdded(queries.len() - failed, failed)).await;
+        if dropped > 0 {
+            respond(ctx, msg, Reply::QueriesDropped(dropped, max)).await;
+        }
+        if quota_exceeded {
+            respond(ctx, msg, Reply::QuotaExceeded).await;
+        }
+        return Ok(());
+    }
+
+    // a link with times after it plays just that part, ex. "play <link> 0:30 1:15"
+    let (message, clip) = match split_clip(message) {
+        Some((link, start, end)) => (link, Some((start, end))),
+        None => (message, None),
+    };
+
+    // Searches the song
+    match get_songs(ctx, msg, message).await {
+        Ok(batch) if batch.songs.is_empty() => respond(ctx, msg, Reply::NoMatches).await,
+        Ok(mut batch) => {
+            if let Some((start, end)) = clip {
+                // playlists and livestreams can't be clipped
+                let single = batch.songs.len() == 1;
+                let song = &mut batch.songs[0];
+                // an end past the end of the song plays it to the end
+                let end = end.filter(|end| song.duration.is_zero() || *end < song.duration);
+                if !single || song.live || end.is_some_and(|end| end <= start) || (!song.duration.is_zero() && start >= song.duration) {
+                    respond(ctx, msg, Reply::ClipUsage).await;
+                    return Ok(());
+                }
+                song.start = Some(start).filter(|start| !start.is_zero());
+                song.end = end;
+            }
+            enqueue_or_play(ctx, msg, call_lock.clone(), Some(call), queue_lock, batch, front).await?
+        }
+        Err(why) => respond(ctx, msg, why.reply()).await,
+    }
+
+    Ok(())
+}
+
+// A link followed by a start time and optionally an end time
+fn split_clip(message: &str) -> Option<(&str, Duration, Option<Duration>)> {
+    let mut words = message.split_whitespace();
+    let link = words.next().filter(|link| link.starts_with("http"))?;
+    let start = parse_timestamp(words.next()?)?;
+    let end = match words.next() {
+        Some(end) => Some(parse_timestamp(end)?),
+        None => None,
+    };
+    if words.next().is_some() {
+        return None;
+    }
+    Some((link, start, end))
+}
+
+// Queries pasted one per line, or links separated by commas. Searches aren't split on commas
+// so ones like "Hello, Adele" stay whole.
+fn split_queries(message: &str) -> Vec<&str> {
+    let lines = message.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<_>>();
+    if lines.len() > 1 {
+        return lines;
+    }
+
+    let parts = message.split(',').map(str::trim).filter(|part| !part.is_empty()).collect::<Vec<_>>();
+    if parts.len() > 1 && parts.iter().all(|part| part.starts_with("http") || part.starts_with("spotify:")) {
+        return parts;
+    }
+    vec![message]
+}
+
+// Links and Spotify URIs in a chat message, including ones wrapped in <> to hide their embeds
+fn links_in(content: &str) -> Vec<&str> {
+    content
+        .split_whitespace()
+        .map(|word| word.trim_start_matches('<').trim_end_matches('>'))
+        .filter(|word| word.starts_with("http://") || word.starts_with("https://") || word.starts_with("spotify:"))
+        .collect()
+}
+
+// Starts the first song if nothing is playing, then adds the rest to the back (or front) of the queue.
+// Adding several songs at once (ex. a playlist) sends one summary instead of an embed per song.
+async fn enqueue_or_play(ctx: &Context, msg: &Message, call_lock: Arc<Mutex<Call>>, call: Option<MutexGuard<'_, Call>>, server_queue_lock: Arc<Mutex<ServerQueue>>, batch: SongBatch, front: bool) -> CommandResult {
+    let mut server_queue = server_queue_lock.lock().await;
+    let count = batch.songs.len();
+    let total_duration = batch.songs.iter().map(|song| song.duration).sum::<Duration>();
+    let first_titles = batch.songs.iter().take(5).map(|song| song.title_with_link()).collect::<Vec<_>>();
+    let mut songs = batch.songs.into_iter().map(|song| Song { requester: Some(msg.author.id), ..song });
+
+    if server_queue.now_playing.is_none() {
+        let mut song = songs.next().unwrap();
+        // they just asked for it, so there's no need to ping them
+        song.requester = None;
+
+        if play_song(ctx, msg.channel_id, call_lock, call, &mut song, server_queue_lock.clone(), server_queue.eq).await {
+            return Ok(());
+        }
+
+        // move at the very end
+        server_queue.now_playing = Some(song);
+    } else if count == 1 {
+        let song = songs.next().unwrap();
+        let position = if front { 1 } else { server_queue.queue.len() + 1 };
+    
+        if front {
+            server_queue.push_front(song);
+        } else {
+            server_queue.push_back(song);
+        }
+
+        if update_live_queue(ctx, &server_queue).await {
+            let _ = msg.react(&ctx.http, '✅').await;
+        } else {
+            let song = &server_queue.queue[position - 1];
+            // warn about accidental duplicates without blocking them
+            let duplicate = server_queue.queue.iter()
+                .enumerate()
+                .find(|(i, queued)| *i != position - 1 && queued.source == song.source)
+                .map(|(i, _)| i + 1);
+            send_added_to_queue(ctx, msg, song, position, duplicate).await?;
+        }
+        return Ok(());
+    }
+
+    if front {
+        for song in songs.rev() {
+            server_queue.push_front(song);
+        }
+    } else {
+        for song in songs {
+            server_queue.push_back(song);
+        }
+    }
+
+    // the live queue shows what was added, so only react to the command
+    if update_live_queue(ctx, &server_queue).await {
+        let _ = msg.react(&ctx.http, '✅').await;
+    } else if count > 1 {
+        // embed titles are limited to 256 characters
+        let title = match batch.playlist_name {
+            Some(name) => format!("Added {}", truncate(&name, 200)),
+            None => format!("Added {} songs", count),
+        };
+        let mut description = first_titles.join("\n");
+        if count > first_titles.len() {
+            description.push_str(&format!("\n...and {} more", count - first_titles.len()));
+        }
+
+        let mut embed = CreateEmbed::default();
+        embed.title(title)
+            .description(description)
+            .field("Tracks", count, true)
+            .field("Total duration", format_duration(&total_duration), true);
+        check_msg(send_embed(ctx, msg.channel_id, embed, None).await);
+    }
+
+    Ok(())
+}
+
+// Songs resolved from one query, named when they came from a playlist or album
+struct SongBatch {
+    songs: Vec<Song>,
+    playlist_name: Option<String>,
+}
+
+impl From<Vec<Song>> for SongBatch {
+    fn from(songs: Vec<Song>) -> SongBatch {
+        SongBatch { songs, playlist_name: None }
+    }
+}
+
+async fn send_added_to_queue(ctx: &Context, msg: &Message, song: &Song, position: usize, duplicate: Option<usize>) -> CommandResult {
+    let avatar_url = ctx.http.get_current_user().await?.avatar_url();
+    let linked_title = song.title_with_link();
+    let track_duration = format_duration(&song.duration);
+
+    let mut embed = CreateEmbed::default();
+    embed.author(|a| {
+        a.name("Added to queue");
+
+        if let Some(avatar_url) = &avatar_url {
+            a.icon_url(avatar_url);
+        }
+        a
+    })
+        .description(format!("**{}**", linked_title))
+        .field("Channel", &song.artist, true)
+        .field("Song Duration", track_duration, true)
+        .field("Time until playing", "todo", true)
+        .field("Position in queue", position, false);
+    if let Some(duplicate) = duplicate {
+        embed.footer(|f| f.text(format!("⚠️ This song is already in the queue at position {}", duplicate)));
+    }
+    if let Some(url) = song.thumbnail_url.as_ref().or(avatar_url.as_ref()) {
+        embed.thumbnail(url);
+    }
+    check_msg(send_embed(ctx, msg.channel_id, embed, None).await);
+
+    Ok(())
+}
+
+// Starts a song and announces it. Returns true if it couldn't be started
+async fn play_song(ctx: &Context, text_channel: ChannelId, call_lock: Arc<Mutex<Call>>, call: Option<MutexGuard<'_, Call>>, song: &mut Song, server_queue: Arc<Mutex<ServerQueue>>, eq: EqPreset) -> bool {
+    if start_song(ctx, text_channel, call_lock, call, song, server_queue, eq).await {
+        return true;
+    }
+
+    let guild_id = ctx.cache.guild_channel_field(text_channel, |channel| channel.guild_id).await;
+    let ping_requester = match guild_id {
+        Some(guild_id) => get_settings(ctx).await.get(guild_id, |settings| settings.ping_requester).await,
+        None => false,
+    };
+    match song.requester.take() {
+        Some(requester) if ping_requester => {
+            say(ctx, text_channel, guild_id, Reply::PlayingFor(&song.short_title(), &requester.mention().to_string())).await;
+        }
+        _ => say(ctx, text_channel, guild_id, Reply::Playing(&song.short_title())).await,
+    }
+    false
+}
+
+// Starts a song without announcing it, for restarting the song that's already playing (ex. to apply an equalizer).
+// Obtains a lock from call_lock, make sure locks are not held earlier in the call stack
+async fn start_song(ctx: &Context, text_channel: ChannelId, call_lock: Arc<Mutex<Call>>, call: Option<MutexGuard<'_, Call>>, song: &mut Song, server_queue: Arc<Mutex<ServerQueue>>, eq: EqPreset) -> bool {
+    let guild_id = ctx.cache.guild_channel_field(text_channel, |channel| channel.guild_id).await;
+    let ytdl_config = get_ytdl_config(ctx).await;
+    let source = loop {
+        match song.source.as_input(ytdl_config.clone(), eq).await {
+            Ok(source) => break source,
+            Err(why) => {
+                println!("Err starting source: {:?}", why);
+                get_source_stats(ctx).await.record_playback(song.source.kind_name(), false);
+
+                // top search results can be unplayable (ex. region locked), so fall back to the next ones
+                if !song.use_next_alternate() {
+                    say(ctx, text_channel, guild_id, Reply::SourceError).await;
+                    return true;
+                }
+            },
+        }
+    };
+
+    // cannot use .unwrap_or because locking val must be lazy
+    // cannot use .unwrap_or_else because closure cannot be async
+    let mut call = match call  {
+        Some(call) => call,
+        None => call_lock.lock().await,
+    };
+    let track = call.play_source(source);
+
+    // the saved volume applies from the first song after joining onwards
+    if let Some(guild_id) = guild_id {
+        let volume = get_settings(ctx).await.get(guild_id, |settings| settings.volume).await;
+        if let Err(why) = track.set_volume(volume as f32 / 100.0) {
+            println!("Err setting volume: {:?}", why);
+        }
+    }
+
+    if let Some(start) = song.start {
+        if let Err(why) = track.seek_time(start) {
+            println!("Err seeking to start of song: {:?}", why);
+        }
+    }
+
+    // youtube-dl opening the source doesn't mean it plays, so it only counts as played once it gets going
+    let _ = track.add_event(
+        Event::Delayed(UNPLAYABLE_TIME),
+        PlaybackCounter {
+            stats: get_source_stats(ctx).await,
+            source: song.source.kind_name(),
+        },
+    );
+
+    // clips are stopped at their end, which moves on to the next song like a skip
+    if let Some(end) = song.end {
+        let _ = track.add_event(Event::Periodic(CLIP_CHECK_INTERVAL, None), ClipEndNotifier { end });
+    }
+
+    let send_call_lock = Arc::downgrade(&call_lock.clone());
+
+    // song ends
+    let _ = track.add_event(
+        Event::Track(TrackEvent::End),
+        SongEndNotifier {
+            text_channel,
+            ctx: ctx.clone(),
+            server_queue: server_queue.clone(),
+            call_lock: send_call_lock,
+        },
+    );
+
+    // move track into song
+    song.handle.replace(track);
+
+    false
+}
+
+// A track that ends within this much playing time never really played (ex. a region locked or deleted video)
+const UNPLAYABLE_TIME: Duration = Duration::from_secs(2);
+
+struct PlaybackCounter {
+    stats: Arc<SourceStats>,
+    source: &'static str,
+}
+
+#[async_trait]
+impl VoiceEventHandler for PlaybackCounter {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        self.stats.record_playback(self.source, true);
+        None
+    }
+}
+
+// How often a clip's position is checked, so it stops at most this long after its end
+const CLIP_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+struct ClipEndNotifier {
+    end: Duration,
+}
+
+#[async_trait]
+impl VoiceEventHandler for ClipEndNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(&[(state, handle)]) = ctx {
+            if state.position >= self.end {
+                if let Err(why) = handle.stop() {
+                    println!("Err stopping clip: {:?}", why);
+                }
+                return Some(Event::Cancel);
+            }
+        }
+        None
+    }
+}
+
+// Where to announce songs. When the channel was deleted, falls back to the music channel and then the system channel
+async fn announce_channel(ctx: &Context, guild_id: GuildId, channel_id: ChannelId) -> ChannelId {
+    let music_channel = get_settings(ctx).await.get(guild_id, |settings| settings.command_channel).await.map(ChannelId);
+    let system_channel = ctx.cache.guild_field(guild_id, |guild| guild.system_channel_id).await.flatten();
+
+    for candidate in [Some(channel_id), music_channel, system_channel].into_iter().flatten() {
+        if ctx.cache.guild_channel(candidate).await.is_some() {
+            return candidate;
+        }
+    }
+    channel_id
+}
+
+struct SongEndNotifier {
+    // sending message, looking up autoplay songs
+    text_channel: ChannelId,
+    ctx: Context,
+    // shifting queue
+    server_queue: Arc<Mutex<ServerQueue>>,
+    // playing song
+    call_lock: Weak<Mutex<Call>>,
+}
+
+#[async_trait]
+impl VoiceEventHandler for SongEndNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let Some(call_lock) = self.call_lock.upgrade() {
+            let mut queue = self.server_queue.lock().await;
+            // the channel songs were requested in may have been deleted since, later songs announce in the fallback
+            let text_channel = announce_channel(&self.ctx, queue.guild_id, self.text_channel).await;
+            if text_channel != self.text_channel {
+                queue.text_channel = Some(text_channel);
+            }
+
+            if let EventContext::Track(&[(state, ended)]) = ctx {
+                let current = queue.now_playing.as_ref().and_then(|song| song.handle.as_ref());
+
+                // Ignore tracks that were already replaced, ex. by clearnp
+                if current.is_none_or(|handle| handle.uuid() != ended.uuid()) {
+                    return None;
+                }
+
+                // Songbird has no error event, a stream that drops out just ends early.
+                // Skipped songs are Stopped instead of Ended so they don't count.
+                let eq = queue.eq;
+                let guild_id = queue.guild_id;
+                if let Some(song) = &mut queue.now_playing {
+                    if state.playing == PlayMode::End && state.position + Duration::from_secs(5) < song.duration {
+                        println!("Stream for {} ended early at {:?}", song.title, state.position);
+                        let unplayable = state.play_time < UNPLAYABLE_TIME;
+                        if unplayable {
+                            get_source_stats(&self.ctx).await.record_playback(song.source.kind_name(), false);
+                        }
+
+                        // An expired or rate limited stream url often works when looked up again, so each song gets one retry.
+                        // youtube-dl opens unplayable videos (ex. region locked) fine and they end right away, so then move on to the next search result
+                        if unplayable && !song.retried {
+                            song.retried = true;
+                            if !start_song(&self.ctx, text_channel, call_lock.clone(), None, song, self.server_queue.clone(), eq).await {
+                                return None;
+                            }
+                        } else if unplayable && song.use_next_alternate() {
+                            if !play_song(&self.ctx, text_channel, call_lock.clone(), None, song, self.server_queue.clone(), eq).await {
+                                update_live_queue(&self.ctx, &queue).await;
+                                return None;
+                            }
+                        } else {
+                            say(&self.ctx, text_channel, Some(guild_id), Reply::StreamLost(&song.short_title())).await;
+                        }
+                    }
+                }
+            }
+        
+            let finished = queue.now_playing.take();
+            queue.shift_queue();
+
+            if queue.autoplay && queue.now_playing.is_none() {
+                if let Some(finished) = &finished {
+                    queue.now_playing = autoplay_song(&self.ctx, finished).await;
+                }
+            }
+            let eq = queue.eq;
+            if let Some(now_playing) = &mut queue.now_playing {
+                play_song(&self.ctx, text_channel, call_lock, None, now_playing, self.server_queue.clone(), eq).await;
+            }
+            update_live_queue(&self.ctx, &queue).await;
+        }
+
+        //check_msg(self.text_channel.say(&self.ctx.http, "Song ended!").await);
+        None
+    }
+}
+
+const QUEUE_PAGE_SIZE: usize = 10;
+// how long the buttons on a queue message keep working
+const QUEUE_BUTTON_TIMEOUT: Duration = Duration::from_secs(600);
+const QUEUE_PREVIOUS: &str = "queue_previous";
+const QUEUE_NEXT: &str = "queue_next";
+
+pub(crate) struct QueuePagesKey;
+impl TypeMapKey for QueuePagesKey {
+    type Value = Arc<RwLock<HashMap<MessageId, QueuePage>>>;
+}
+
+// Which page a queue message with buttons is showing
+pub(crate) struct QueuePage {
+    page: usize,
+    // the songs of whoever ran the command stay marked as the pages turn
+    viewer: String,
+    grouped: bool,
+    posted: Instant,
+}
+
+// Shows a page of the queue, ex. "queue 3", or the page holding a position with "queue jump 45"
+// "queue --grouped" collapses songs queued one after another by the same person
+#[command]
+#[aliases("q")]
+async fn queue(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let (_, guild, server_queue_lock) = match resolve_call(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+
+    let grouped = args.current().is_some_and(|arg| arg.eq_ignore_ascii_case("--grouped"));
+    if grouped {
+        args.advance();
+    }
+
+    let summary_size = get_queues(ctx).await.summary_size;
+    let server_queue = server_queue_lock.lock().await;
+
+    // listing a giant queue (ex. several imported playlists) lags, so only a page that was asked for is shown
+    if args.is_empty() && server_queue.queue.len() > summary_size {
+        let mut embed = CreateEmbed::default();
+        embed.title(format!("Queue for {}", guild.name))
+            .description(queue_summary(&server_queue))
+            .footer(|f| f.text("Too many songs to list, use queue <page>, queue jump <position> or queuefind <name>"));
+        send_embed(ctx, msg.channel_id, embed, None).await?;
+        return Ok(());
+    }
+    let pages = queue_pages(&server_queue, grouped);
+
+    let page = if args.is_empty() {
+        1
+    } else if args.current().is_some_and(|arg| arg.eq_ignore_ascii_case("jump")) {
+        args.advance();
+        match args.single::<usize>() {
+            // grouped pages hold runs of songs, so find the run the position is in
+            Ok(position) if grouped => {
+                let run = requester_runs(&server_queue).iter().position(|run| run.contains(&position.saturating_sub(1))).unwrap_or(0);
+                run / QUEUE_PAGE_SIZE + 1
+            }
+            Ok(position) => position.saturating_sub(1) / QUEUE_PAGE_SIZE + 1,
+            Err(_) => {
+                respond(ctx, msg, Reply::QueueJumpUsage).await;
+                return Ok(());
+            }
+        }
+    } else {
+        match args.single::<usize>() {
+            Ok(page) => page,
+            Err(_) => {
+                respond(ctx, msg, Reply::QueueUsage).await;
+                return Ok(());
+            }
+        }
+    }.clamp(1, pages);
+
+    let viewer = msg.author.tag();
+    let embed = queue_embed(&server_queue, &guild.name, page, &viewer, grouped).await;
+
+    let buttons = if pages > 1 { Some(queue_buttons(page, pages)) } else { None };
+    let message = send_embed(ctx, msg.channel_id, embed, buttons).await?;
+
+    if pages > 1 {
+        let page_states_lock = get_queue_pages(ctx).await;
+        let mut page_states = page_states_lock.write().await;
+        page_states.retain(|_, state| state.posted.elapsed() < QUEUE_BUTTON_TIMEOUT);
+        page_states.insert(message.id, QueuePage { page, viewer, grouped, posted: Instant::now() });
+    }
+
+    Ok(())
+}
+
+// Turns the page of a queue message when one of its buttons is pressed
+pub(crate) async fn queue_button(ctx: &Context, interaction: &MessageComponentInteraction) {
+    let forward = match interaction.data.custom_id.as_str() {
+        QUEUE_PREVIOUS => false,
+        QUEUE_NEXT => true,
+        _ => return,
+    };
+    let guild_id = match interaction.guild_id {
+        Some(guild_id) => guild_id,
+        None => return,
+    };
+
+    let page_states_lock = get_queue_pages(ctx).await;
+    let mut page_states = page_states_lock.write().await;
+
+    let state = match page_states.get_mut(&interaction.message.id) {
+        Some(state) if state.posted.elapsed() < QUEUE_BUTTON_TIMEOUT => state,
+        _ => {
+            // the message is too old to page through, so take its buttons away
+            page_states.remove(&interaction.message.id);
+            if let Err(why) = interaction.create_interaction_response(&ctx.http, |r| {
+                r.kind(InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|d| d.components(|c| c))
+            }).await {
+                println!("Error removing queue buttons: {:?}", why);
+            }
+            return;
+        }
+    };
+
+    let server_queue_lock = get_queues(ctx).await.queue_or_create(&guild_id).await;
+    let server_queue = server_queue_lock.lock().await;
+    // the queue may have shrunk since the message was sent
+    let pages = queue_pages(&server_queue, state.grouped);
+    state.page = if forward { state.page + 1 } else { state.page.saturating_sub(1) }.clamp(1, pages);
+
+    let guild_name = guild_id.name(&ctx.cache).await.unwrap_or_default();
+    let embed = queue_embed(&server_queue, &guild_name, state.page, &state.viewer, state.grouped).await;
+    let buttons = queue_buttons(state.page, pages);
+
+    let plain = !use_embeds(ctx, interaction.channel_id).await;
+
+    if let Err(why) = interaction.create_interaction_response(&ctx.http, |r| {
+        r.kind(InteractionResponseType::UpdateMessage)
+            .interaction_response_data(|d| {
+                if plain {
+                    d.content(embed_text(&embed));
+                } else {
+                    d.add_embed(embed);
+                }
+                d.set_components(buttons)
+            })
+    }).await {
+        println!("Error turning queue page: {:?}", why);
+    }
+}
+
+async fn queue_embed(server_queue: &ServerQueue, guild_name: &str, page: usize, viewer: &str, grouped: bool) -> CreateEmbed {
+    let pages = queue_pages(server_queue, grouped);
+    let mut description = queue_description(server_queue, page, Some(viewer), grouped);
+
+    // footers don't render timestamps, so the end time goes under the songs
+    if let Some(now_playing) = &server_queue.now_playing {
+        let elapsed = match &now_playing.handle {
+            Some(handle) => handle.get_info().await.map(|info| info.position).unwrap_or_default(),
+            None => Duration::ZERO,
+        };
+        let remaining = now_playing.duration.saturating_sub(elapsed) + server_queue.total_duration;
+        let ends_at = (SystemTime::now() + remaining).duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+        description.push_str(&format!("\n\n`Total:` {} | Ends <t:{}:R>", format_duration(&remaining), ends_at));
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed.title(format!("Queue for {}", guild_name))
+        .description(description)
+        .footer(|f| f.text(format!("Page {} of {}", page, pages)));
+    embed
+}
+
+fn queue_buttons(page: usize, pages: usize) -> CreateComponents {
+    let mut components = CreateComponents::default();
+    components.create_action_row(|row| {
+        row.create_button(|b| b.style(ButtonStyle::Secondary).label("◀️").custom_id(QUEUE_PREVIOUS).disabled(page <= 1))
+            .create_button(|b| b.style(ButtonStyle::Secondary).label("▶️").custom_id(QUEUE_NEXT).disabled(page >= pages))
+    });
+    components
+}
+
+fn now_playing_header(server_queue: &ServerQueue) -> String {
+    match &server_queue.now_playing {
+        Some(now_playing) => format!("__Now Playing:__\n{} | `{} Requested by: {}`", now_playing.title_with_link(), format_duration(&now_playing.duration), now_playing.author),
+        None => "__Now Playing:__\nNothing".to_owned(),
+    }
+}
+
+// The now playing song and how much is queued after it, without listing the queue
+fn queue_summary(server_queue: &ServerQueue) -> String {
+    let mut description = now_playing_header(server_queue);
+    description.push_str(&format!("\n\n__Up Next:__\n`{}` songs | `Total:` {}", server_queue.queue.len(), format_duration(&server_queue.total_duration)));
+
+    description
+}
+
+fn queue_pages(server_queue: &ServerQueue, grouped: bool) -> usize {
+    let entries = if grouped { requester_runs(server_queue).len() } else { server_queue.queue.len() };
+    entries.div_ceil(QUEUE_PAGE_SIZE).max(1)
+}
+
+// Index ranges of songs queued one after another by the same person
+fn requester_runs(server_queue: &ServerQueue) -> Vec<Range<usize>> {
+    let mut runs: Vec<Range<usize>> = Vec::new();
+    for (i, song) in server_queue.queue.iter().enumerate() {
+        match runs.last_mut() {
+            Some(run) if server_queue.queue[run.start].author == song.author => run.end = i + 1,
+            _ => runs.push(i..i + 1),
+        }
+    }
+    runs
+}
+
+// Renders the now playing song and one page of the queue, pages start at 1
+// Songs requested by the viewer are marked, the live queue has no single viewer so it passes None
+fn queue_description(server_queue: &ServerQueue, page: usize, viewer: Option<&str>, grouped: bool) -> String {
+    let mut description = now_playing_header(server_queue);
+    if grouped && !server_queue.queue.is_empty() {
+        let entries = requester_runs(server_queue)
+            .into_iter()
+            .skip((page - 1) * QUEUE_PAGE_SIZE)
+            .take(QUEUE_PAGE_SIZE)
+            .map(|run| {
+                let first = &server_queue.queue[run.start];
+                let marker = if viewer == Some(first.author.as_str()) { "▸ " } else { "" };
+                if run.len() == 1 {
+                    return format!("{}{}", marker, queue_entry(run.start + 1, first));
+                }
+                let duration = server_queue.queue.range(run.clone()).map(|song| song.duration).sum::<Duration>();
+                format!("{}`{}-{}.` **Requested by {} — {} songs** | `{}`", marker, run.start + 1, run.end, first.author, run.len(), format_duration(&duration))
+            })
+            .collect::<Vec<_>>();
+
+        description.push_str("\n\n__Up Next:__\n");
+        description.push_str(&entries.join("\n\n"));
+    } else if !server_queue.queue.is_empty() {
+        let entries = server_queue.queue
+            .iter()
+            .enumerate()
+            .skip((page - 1) * QUEUE_PAGE_SIZE)
+            .take(QUEUE_PAGE_SIZE)
+            .map(|(i, song)| {
+                let marker = if viewer == Some(song.author.as_str()) { "▸ " } else { "" };
+                format!("{}{}", marker, queue_entry(i + 1, song))
+            })
+            .collect::<Vec<_>>();
+
+        description.push_str("\n\n__Up Next:__\n");
+        description.push_str(&entries.join("\n\n"));
+    }
+
+    description
+}
+
+fn queue_entry(position: usize, song: &Song) -> String {
+    format!("`{}.` {} | `{} Requested by: {}`", position, song.title_with_link(), format_duration(&song.duration), song.author)
+}
+
+// How many songs are shown either side of the ones a queue edit changed
+const SNIPPET_CONTEXT: usize = 2;
+
+// The queue entries around the changed indexes, with the changed ones marked. Stops after a page of entries
+fn queue_snippet(server_queue: &ServerQueue, changed: RangeInclusive<usize>) -> String {
+    let first = changed.start().saturating_sub(SNIPPET_CONTEXT);
+    let last = (changed.end() + SNIPPET_CONTEXT).min(server_queue.queue.len().saturating_sub(1));
+    let mut entries = server_queue.queue
+        .range(first..=last)
+        .enumerate()
+        .take(QUEUE_PAGE_SIZE)
+        .map(|(i, song)| {
+            let marker = if changed.contains(&(first + i)) { "▸ " } else { "" };
+            format!("{}{}", marker, queue_entry(first + i + 1, song))
+        })
+        .collect::<Vec<_>>();
+    if last + 1 - first > QUEUE_PAGE_SIZE {
+        entries.push("…".to_owned());
+    }
+    entries.join("\n\n")
+}
+
+// Replies to a queue edit along with the part of the queue it changed, so it can be checked without the queue command
+async fn respond_with_snippet(ctx: &Context, msg: &Message, reply: Reply<'_>, server_queue: &ServerQueue, changed: RangeInclusive<usize>) {
+    let mut embed = CreateEmbed::default();
+    embed.title(reply.render(locale(ctx, msg.guild_id).await))
+        .description(queue_snippet(server_queue, changed));
+    check_msg(send_embed(ctx, msg.channel_id, embed, None).await);
+}
+
+// Lists the queued songs whose title or artist contains the search text
+#[command]
+#[only_in(guilds)]
+#[aliases("qf")]
+async fn queuefind(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let (_, guild, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    let query = truncate(args.message().trim(), 100);
+    let search = query.to_lowercase();
+
+    if search.is_empty() {
+        respond(ctx, msg, Reply::QueueFindUsage).await;
+        return Ok(());
+    }
+
+    let queue = queue_lock.lock().await;
+    let matches = queue_matches(&queue, &search);
+
+    if matches.is_empty() {
+        respond(ctx, msg, Reply::NoQueueMatches(&query)).await;
+        return Ok(());
+    }
+
+    send_queue_matches(ctx, msg, format!("Queue for {} matching \"{}\"", guild.name, query), &queue, &matches).await
+}
+
+// Indexes of the queued songs whose title or artist contains the lowercase search text
+fn queue_matches(server_queue: &ServerQueue, search: &str) -> Vec<usize> {
+    server_queue.queue
+        .iter()
+        .enumerate()
+        .filter(|(_, song)| song.title.to_lowercase().contains(search) || song.artist.to_lowercase().contains(search))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+async fn send_queue_matches(ctx: &Context, msg: &Message, title: String, server_queue: &ServerQueue, matches: &[usize]) -> CommandResult {
+    let mut description = matches.iter().take(10).map(|&i| queue_entry(i + 1, &server_queue.queue[i])).collect::<Vec<_>>().join("\n\n");
+    if matches.len() > 10 {
+        description.push_str(&format!("\n\n...and {} more", matches.len() - 10));
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed.title(title).description(description);
+    send_embed(ctx, msg.channel_id, embed, None).await?;
+
+    Ok(())
+}
+
+// Skips straight to the queued song matching a name, dropping the songs before it
+#[command]
+#[only_in(guilds)]
+#[checks(DJ)]
+async fn jumpto(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let (call_lock, guild, queue_lock) = match resolve_call(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+    let query = truncate(args.message().trim(), 100);
+    let search = query.to_lowercase();
+
+    if search.is_empty() {
+        respond(ctx, msg, Reply::JumpToUsage).await;
+        return Ok(());
+    }
+
+    let mut queue = queue_lock.lock().await;
+    let index = match queue_matches(&queue, &search)[..] {
+        [] => {
+            respond(ctx, msg, Reply::NoQueueMatches(&query)).await;
+            return Ok(());
+        }
+        [index] => index,
+        ref matches => {
+            let title = format!("Several songs in {} match \"{}\", try a longer name", guild.name, query);
+            return send_queue_matches(ctx, msg, title, &queue, matches).await;
+        }
+    };
+
+    // the skipped songs can be brought back with undo
+    queue.save_undo();
+    queue.undo_removed = queue.drain_front(index);
+    respond(ctx, msg, Reply::JumpedTo(&queue.queue[0].short_title())).await;
+
+    // stopping the current song makes SongEndNotifier start the one now at the front
+    match queue.now_playing.as_ref().and_then(|song| song.handle.as_ref()) {
+        Some(handle) => {
+            let _ = handle.stop();
+        }
+        None => {
+            queue.shift_queue();
+            let eq = queue.eq;
+            if let Some(now_playing) = &mut queue.now_playing {
+                play_song(ctx, msg.channel_id, call_lock, None, now_playing, queue_lock.clone(), eq).await;
+            }
+        }
+    }
+    update_live_queue(ctx, &queue).await;
+    Ok(())
+}
+
+// Swaps the author's last queued song, or the song playing if they requested it, for the next search result
+// "wrongsong <search>" searches again instead
+#[command]
+#[only_in(guilds)]
+#[aliases("badmatch")]
+async fn wrongsong(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let (call_lock, guild, queue_lock) = match resolve_call(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+    let author = msg.author.tag();
+    let query = args.message().trim();
+
+    // search before locking the queue so other commands aren't held up
+    let searched = if query.is_empty() {
+        None
+    } else {
+        match get_song(ctx, msg, query).await {
+            Ok(Some(song)) => Some(song),
+            Ok(None) => {
+                respond(ctx, msg, Reply::NoMatches).await;
+                return Ok(());
+            }
+            Err(why) => {
+                respond(ctx, msg, why.reply()).await;
+                return Ok(());
+            }
+        }
+    };
+
+    let mut queue = queue_lock.lock().await;
+    // None when replacing the now playing song
+    let index = match queue.queue.iter().rposition(|song| song.author == author) {
+        Some(index) => Some(index),
+        None if queue.now_playing.as_ref().is_some_and(|song| song.author == author) => None,
+        None => {
+            respond(ctx, msg, Reply::NothingToReplace).await;
+            return Ok(());
+        }
+    };
+    let replacement = match searched {
+        Some(song) => song,
+        // the song moves on to its next search result in place, the rest stay around in case that one is wrong too
+        None => {
+            match index {
+                Some(index) => {
+                    if !queue.use_next_alternate(index) {
+                        respond(ctx, msg, Reply::NoOtherResults).await;
+                        return Ok(());
+                    }
+                    respond(ctx, msg, Reply::ReplacedWith(&queue.queue[index].short_title())).await;
+                }
+                None => {
+                    let eq = queue.eq;
+                    let now_playing = queue.now_playing.as_mut().unwrap();
+                    // the handle is taken first so the old track ending is ignored
+                    let handle = now_playing.handle.take();
+                    if !now_playing.use_next_alternate() {
+                        now_playing.handle = handle;
+                        respond(ctx, msg, Reply::NoOtherResults).await;
+                        return Ok(());
+                    }
+                    if let Some(handle) = handle {
+                        let _ = handle.stop();
+                    }
+                    respond(ctx, msg, Reply::ReplacedWith(&now_playing.short_title())).await;
+                    play_song(ctx, msg.channel_id, call_lock, None, now_playing, queue_lock.clone(), eq).await;
+                }
+            }
+            update_live_queue(ctx, &queue).await;
+            return Ok(());
+        }
+    };
+    respond(ctx, msg, Reply::ReplacedWith(&replacement.short_title())).await;
+
+    match index {
+        Some(index) => {
+            queue.replace(index, replacement);
+        }
+        None => {
+            queue.push_front(replacement);
+            // stopping the current song makes SongEndNotifier start the replacement
+            match queue.now_playing.as_ref().and_then(|song| song.handle.as_ref()) {
+                Some(handle) => {
+                    let _ = handle.stop();
+                }
+                None => {
+                    queue.shift_queue();
+                    let eq = queue.eq;
+                    if let Some(now_playing) = &mut queue.now_playing {
+                        play_song(ctx, msg.channel_id, call_lock, None, now_playing, queue_lock.clone(), eq).await;
+                    }
+                }
+            }
+        }
+    }
+    update_live_queue(ctx, &queue).await;
+    Ok(())
+}
+
+// Keeps one queue message up to date in this channel instead of replying to every change
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn livequeue(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let (_, guild, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    let settings = get_settings(ctx).await;
+
+    if args.message().eq_ignore_ascii_case("off") {
+        settings.update(guild.id, |settings| settings.live_queue = None).await;
+        respond(ctx, msg, Reply::LiveQueueStopped).await;
+        return Ok(());
+    }
+
+    let description = queue_description(&*queue_lock.lock().await, 1, None, false);
+    let mut embed = CreateEmbed::default();
+    embed.title(format!("Queue for {}", guild.name)).description(description);
+    let message = send_embed(ctx, msg.channel_id, embed, None).await?;
+
+    let live_queue = LiveQueue { channel_id: message.channel_id.0, message_id: message.id.0 };
+    settings.update(guild.id, |settings| settings.live_queue = Some(live_queue)).await;
+    Ok(())
+}
+
+// Edits the guild's live queue message, posting a new one if it was deleted.
+// Returns false when the guild doesn't use a live queue.
+async fn update_live_queue(ctx: &Context, server_queue: &ServerQueue) -> bool {
+    let guild_id = server_queue.guild_id;
+    let settings = get_settings(ctx).await;
+    let live_queue = match settings.get(guild_id, |settings| settings.live_queue).await {
+        Some(live_queue) => live_queue,
+        None => return false,
+    };
+
+    let title = format!("Queue for {}", ctx.cache.guild_field(guild_id, |guild| guild.name.clone()).await.unwrap_or_default());
+    let description = queue_description(server_queue, 1, None, false);
+    let channel_id = ChannelId(live_queue.channel_id);
+
+    let mut embed = CreateEmbed::default();
+    embed.title(title).description(description);
+    let edited = edit_embed(ctx, channel_id, MessageId(live_queue.message_id), embed.clone()).await;
+
+    // only a deleted message is replaced, other errors (ex. rate limits) would leave duplicates behind
+    if let Err(why) = edited {
+        if !is_deleted(&why) {
+            println!("Error updating live queue message: {:?}", why);
+            return true;
+        }
+        match send_embed(ctx, channel_id, embed, None).await {
+            Ok(message) => {
+                let live_queue = LiveQueue { channel_id: channel_id.0, message_id: message.id.0 };
+                settings.update(guild_id, |settings| settings.live_queue = Some(live_queue)).await;
+            }
+            Err(why) => println!("Error replacing live queue message: {:?}", why),
+        }
+    }
+
+    true
+}
+
+#[command]
+#[only_in(guilds)]
+async fn pause(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let (_, guild, queue_lock) = match resolve_call(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+
+    let mut queue = queue_lock.lock().await;
+    match queue.now_playing.as_ref().and_then(|song| song.handle.clone()) {
+        Some(handle) => {
+            handle.pause()?;
+            // a paused bot still holds the voice channel, so it counts as idle
+            queue.idle_since = Some(Instant::now());
+            respond(ctx, msg, Reply::Paused).await;
+        }
+        None => nothing_playing(ctx, msg).await?,
+    }
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[aliases("unpause")]
+async fn resume(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let (_, guild, queue_lock) = match resolve_call(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+
+    let mut queue = queue_lock.lock().await;
+    match queue.now_playing.as_ref().and_then(|song| song.handle.clone()) {
+        Some(handle) => {
+            handle.play()?;
+            queue.paused_alone = false;
+            queue.idle_since = None;
+            respond(ctx, msg, Reply::Resumed).await;
+        }
+        None => nothing_playing(ctx, msg).await?,
+    }
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[aliases("s", "fs")]
+async fn skip(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let (handler_lock, guild, queue_lock) = match resolve_call(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+
+    // songs have to play for a while before non-DJs can skip them, if the server set a delay
+    let delay = Duration::from_secs(get_settings(ctx).await.get(guild.id, |settings| settings.skip_delay).await);
+    if !delay.is_zero() && !is_dj(ctx, msg).await {
+        let handle = queue_lock.lock().await.now_playing.as_ref().and_then(|song| song.handle.clone());
+        let played = match handle {
+            Some(handle) => handle.get_info().await.map_or(delay, |info| info.play_time),
+            None => delay,
+        };
+        if played < delay {
+            // round up so it never says 0 seconds
+            respond(ctx, msg, Reply::SkipWait((delay - played).as_secs_f64().ceil() as u64)).await;
+            return Ok(());
+        }
+    }
+
+    let mut handler = handler_lock.lock().await;
+    handler.stop();
+    let queue = queue_lock.lock().await;
+    if let Some(now_playing) = &queue.now_playing {
+        now_playing.handle.as_ref().unwrap().send(songbird::tracks::TrackCommand::Stop)?;
+
+        // the queue shifts later in SongEndNotifier, so the next song is still at the front
+        let reply = match queue.queue.front() {
+            Some(next) => Reply::SkippedTo(&next.short_title()),
+            None => Reply::SkippedEmpty,
+        };
+        respond(ctx, msg, reply).await;
+    } else {
+        nothing_playing(ctx, msg).await?;
+    }
+    Ok(())
+}
+
+// Moves a queued song, either to a position ("move 5 2") or relative to where it is ("move 5 up 2")
+#[command("move")]
+#[only_in(guilds)]
+#[aliases("mv")]
+async fn move_song(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let (_, guild, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+    let mut queue = queue_lock.lock().await;
+    let len = queue.queue.len();
+
+    if len == 0 {
+        respond(ctx, msg, Reply::QueueEmpty).await;
+        return Ok(());
+    }
+
+    let from = match args.single::<usize>() {
+        Ok(from) if from >= 1 && from <= len => from,
+        Ok(_) => {
+            respond(ctx, msg, Reply::PositionRange(len)).await;
+            return Ok(());
+        }
+        Err(_) => return move_usage(ctx, msg).await,
+    };
+    let to = match args.single::<String>().map(|arg| arg.to_lowercase()).as_deref() {
+        Ok("up") => from.saturating_sub(args.single::<usize>().unwrap_or(1)),
+        Ok("down") => from.saturating_add(args.single::<usize>().unwrap_or(1)),
+        Ok(to) => match to.parse::<usize>() {
+            Ok(to) => to,
+            Err(_) => return move_usage(ctx, msg).await,
+        },
+        Err(_) => return move_usage(ctx, msg).await,
+    }.clamp(1, len);
+
+    if !can_edit_song(ctx, msg, &queue.queue[from - 1]).await {
+        respond(ctx, msg, Reply::NotYourSong).await;
+        return Ok(());
+    }
+
+    queue.save_undo();
+    let song = queue.remove(from - 1).unwrap();
+    let title = song.short_title();
+    queue.insert(to - 1, song);
+    update_live_queue(ctx, &queue).await;
+
+    respond_with_snippet(ctx, msg, Reply::Moved(&title, to), &queue, to - 1..=to - 1).await;
+    Ok(())
+}
+
+async fn move_usage(ctx: &Context, msg: &Message) -> CommandResult {
+    respond(ctx, msg, Reply::MoveUsage).await;
+    Ok(())
+}
+
+// Takes a song out of the queue by its position
+#[command]
+#[only_in(guilds)]
+#[aliases("rm")]
+async fn remove(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let (_, guild, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+    let mut queue = queue_lock.lock().await;
+    let len = queue.queue.len();
+
+    if len == 0 {
+        respond(ctx, msg, Reply::QueueEmpty).await;
+        return Ok(());
+    }
+
+    let position = match args.single::<usize>() {
+        Ok(position) if position >= 1 && position <= len => position,
+        Ok(_) => {
+            respond(ctx, msg, Reply::PositionRange(len)).await;
+            return Ok(());
+        }
+        Err(_) => {
+            respond(ctx, msg, Reply::RemoveUsage).await;
+            return Ok(());
+        }
+    };
+
+    if !can_edit_song(ctx, msg, &queue.queue[position - 1]).await {
+        respond(ctx, msg, Reply::NotYourSong).await;
+        return Ok(());
+    }
+
+    queue.save_undo();
+    let song = queue.remove(position - 1).unwrap();
+    let title = song.short_title();
+    queue.undo_removed.push(song);
+    update_live_queue(ctx, &queue).await;
+
+    respond(ctx, msg, Reply::Removed(&title)).await;
+    Ok(())
+}
+
+// Moves all of the author's queued songs to the front, keeping their order
+#[command]
+#[only_in(guilds)]
+#[checks(DJ)]
+#[aliases("mine")]
+async fn myfirst(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let (_, guild, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+    let mut queue = queue_lock.lock().await;
+    let author = msg.author.tag();
+
+    let moved = queue.queue.iter().filter(|song| song.author == author).count();
+    if moved > 0 {
+        queue.save_undo();
+        // a stable sort keeps the order within both groups
+        queue.queue.make_contiguous().sort_by_key(|song| song.author != author);
+        update_live_queue(ctx, &queue).await;
+        respond_with_snippet(ctx, msg, Reply::MovedToFront(moved), &queue, 0..=moved - 1).await;
+    } else {
+        respond(ctx, msg, Reply::MovedToFront(moved)).await;
+    }
+    Ok(())
+}
+
+// Takes repeats out of the queue, ex. after queueing two playlists that share songs
+#[command]
+#[only_in(guilds)]
+#[checks(DJ)]
+#[aliases("dedupe")]
+async fn cleardupes(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let (_, guild, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+    let mut queue = queue_lock.lock().await;
+
+    let removed = queue.remove_duplicates();
+    if removed > 0 {
+        update_live_queue(ctx, &queue).await;
+    }
+
+    respond(ctx, msg, Reply::DuplicatesRemoved(removed)).await;
+    Ok(())
+}
+
+// Empties the queue and keeps the current song ("clear upcoming", the default),
+// or stops the current song too ("clear all")
+#[command]
+#[only_in(guilds)]
+#[checks(DJ)]
+async fn clear(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let (_, guild, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    let all = match args.message().trim().to_lowercase().as_str() {
+        "" | "upcoming" => false,
+        "all" => true,
+        _ => {
+            respond(ctx, msg, Reply::ClearUsage).await;
+            return Ok(());
+        }
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+    let mut queue = queue_lock.lock().await;
+    let upcoming = queue.queue.len();
+
+    let reply = if all {
+        // the queue is emptied first so SongEndNotifier has nothing to start
+        let handle = queue.now_playing.as_ref().and_then(|song| song.handle.clone());
+        let stopped = queue.now_playing.is_some();
+        queue.stop();
+        if let Some(handle) = handle {
+            let _ = handle.stop();
+        }
+        if stopped { Reply::ClearedAll(upcoming) } else { Reply::ClearedUpcoming(upcoming) }
+    } else {
+        queue.clear_queue();
+        Reply::ClearedUpcoming(upcoming)
+    };
+    update_live_queue(ctx, &queue).await;
+
+    respond(ctx, msg, reply).await;
+    Ok(())
+}
+
+const VOLUME_RAMP_TIME: Duration = Duration::from_millis(200);
+const VOLUME_RAMP_STEPS: u32 = 8;
+
+// Shows or sets the volume in percent, saved for the server
+#[command]
+#[only_in(guilds)]
+#[checks(DJ)]
+#[aliases("vol")]
+async fn volume(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let (_, guild, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    let settings = get_settings(ctx).await;
+
+    if args.is_empty() {
+        let volume = settings.get(guild.id, |settings| settings.volume).await;
+        respond(ctx, msg, Reply::Volume(volume)).await;
+        return Ok(());
+    }
+
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+
+    let volume = match args.single::<u32>() {
+        Ok(volume) if volume <= 200 => volume,
+        _ => {
+            respond(ctx, msg, Reply::VolumeRange).await;
+            return Ok(());
+        }
+    };
+    settings.update(guild.id, |settings| settings.volume = volume).await;
+
+    let handle = queue_lock.lock().await.now_playing.as_ref().and_then(|song| song.handle.clone());
+    respond(ctx, msg, Reply::VolumeSet(volume)).await;
+
+    if let Some(handle) = handle {
+        ramp_volume(&handle, volume as f32 / 100.0).await;
+    }
+    Ok(())
+}
+
+// Steps the volume to the target over VOLUME_RAMP_TIME, jumping straight there pops audibly
+async fn ramp_volume(handle: &TrackHandle, target: f32) {
+    // the handle knows the volume even if a previous ramp was cut short
+    let start = match handle.get_info().await {
+        Ok(info) => info.volume,
+        Err(_) => return,
+    };
+    let steps = if (target - start).abs() < 0.25 { 1 } else { VOLUME_RAMP_STEPS };
+
+    let mut interval = tokio::time::interval(VOLUME_RAMP_TIME / steps);
+    for step in 1..=steps {
+        interval.tick().await;
+        if handle.set_volume(start + (target - start) * step as f32 / steps as f32).is_err() {
+            return;
+        }
+    }
+}
+
+// Restores the queue from before the last move or removal
+#[command]
+#[only_in(guilds)]
+#[checks(DJ)]
+async fn undo(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let (_, guild, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+    let mut queue = queue_lock.lock().await;
+
+    let reply = if queue.undo() {
+        update_live_queue(ctx, &queue).await;
+        Reply::Undone
+    } else {
+        Reply::NothingToUndo
+    };
+    respond(ctx, msg, reply).await;
+    Ok(())
+}
+
+// Loops the current song forever, a number of times, or stops looping with "off"
+#[command]
+#[only_in(guilds)]
+#[aliases("loop")]
+async fn repeat(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let (_, guild, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+    let mut queue = queue_lock.lock().await;
+
+    let handle = match queue.now_playing.as_ref().and_then(|song| song.handle.clone()) {
+        Some(handle) => handle,
+        None => return nothing_playing(ctx, msg).await,
+    };
+
+    let reply = if args.is_empty() {
+        handle.enable_loop()?;
+        queue.looping = Some(LoopState::Infinite);
+        Reply::Looping
+    } else if args.message().eq_ignore_ascii_case("off") {
+        handle.disable_loop()?;
+        queue.looping = None;
+        Reply::LoopStopped
+    } else if let Ok(count) = args.single::<usize>() {
+        handle.loop_for(count)?;
+        queue.looping = Some(LoopState::Finite(count));
+        Reply::LoopingTimes(count)
+    } else {
+        Reply::LoopUsage
+    };
+
+    respond(ctx, msg, reply).await;
+    Ok(())
+}
+
+// Keeps playing similar songs once the queue runs out
+#[command]
+#[only_in(guilds)]
+#[aliases("ap")]
+async fn autoplay(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let (_, guild, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+    let mut queue = queue_lock.lock().await;
+
+    queue.autoplay = !queue.autoplay;
+
+    let reply = if queue.autoplay { Reply::AutoplayEnabled } else { Reply::AutoplayDisabled };
+    respond(ctx, msg, reply).await;
+    Ok(())
+}
+
+// Switches now playing between a static embed and one that's edited as the song plays
+#[command]
+#[only_in(guilds)]
+#[checks(DJ)]
+#[aliases("lp")]
+async fn liveprogress(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let (_, _, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    let mut queue = queue_lock.lock().await;
+
+    queue.live_progress = !queue.live_progress;
+
+    let reply = if queue.live_progress { Reply::LiveProgressEnabled } else { Reply::LiveProgressDisabled };
+    respond(ctx, msg, reply).await;
+    Ok(())
+}
+
+// Picks a song to follow `finished` when autoplay is on and the queue is empty
+async fn autoplay_song(ctx: &Context, finished: &Song) -> Option<Song> {
+    let api_access = get_api_access(ctx).await;
+
+    // Spotify recommendations need a Spotify track to seed from
+    if let Some(track_id) = &finished.spotify_id {
+        let recommendations = api_access.get_spotify_recommendations(track_id).await.unwrap_or_default();
+
+        if let Some(track) = recommendations.into_iter().next() {
+            if let Ok(Some(song)) = spotify_song(ctx, &api_access, track, "Autoplay".to_owned()).await {
+                return Some(song);
+            }
+        }
+    }
+
+    // otherwise fall back to another song from the same artist
+    let results = get_search_provider(ctx).await.search(&finished.artist).await.ok()?;
+    let mut next = results.into_iter().find(|song| song.source.url() != finished.source.url())?;
+    next.author = "Autoplay".to_owned();
+
+    Some(next)
+}
+
+#[command]
+#[only_in(guilds)]
+#[checks(DJ)]
+#[aliases("resetnp")]
+async fn clearnp(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let (call_lock, guild, queue_lock) = match resolve_call(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+    let mut queue = queue_lock.lock().await;
+
+    // the handle may already be dead, so errors here are expected
+    if let Some(handle) = queue.now_playing.as_ref().and_then(|song| song.handle.as_ref()) {
+        let _ = handle.stop();
+    }
+    queue.skip();
+    queue.shift_queue();
+
+    respond(ctx, msg, Reply::ClearedCurrent).await;
+
+    let eq = queue.eq;
+    if let Some(now_playing) = &mut queue.now_playing {
+        play_song(ctx, msg.channel_id, call_lock, None, now_playing, queue_lock.clone(), eq).await;
+    }
+    update_live_queue(ctx, &queue).await;
+    Ok(())
+}
+
+// Plays the first queued song now and resumes the interrupted one right after it
+#[command]
+#[only_in(guilds)]
+#[checks(DJ)]
+async fn swapnext(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let (call_lock, guild, queue_lock) = match resolve_call(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+    let mut queue = queue_lock.lock().await;
+
+    if queue.now_playing.is_none() {
+        return nothing_playing(ctx, msg).await;
+    }
+    let mut next = match queue.pop_front() {
+        Some(next) => next,
+        None => {
+            respond(ctx, msg, Reply::QueueEmpty).await;
+            return Ok(());
+        }
+    };
+
+    let mut current = queue.now_playing.take().unwrap();
+    if let Some(handle) = current.handle.take() {
+        // restart the interrupted song where it was stopped
+        if let Ok(info) = handle.get_info().await {
+            current.start = Some(info.position);
+        }
+        let _ = handle.stop();
+    }
+    queue.push_front(current);
+    queue.looping = None;
+    queue.paused_alone = false;
+
+    respond_with_snippet(ctx, msg, Reply::Swapped(&next.short_title()), &queue, 0..=0).await;
+
+    let eq = queue.eq;
+    if !play_song(ctx, msg.channel_id, call_lock, None, &mut next, queue_lock.clone(), eq).await {
+        queue.now_playing = Some(next);
+    }
+    update_live_queue(ctx, &queue).await;
+    Ok(())
+}
+
+// Sets the equalizer preset used for every song, or shows the current one
+#[command]
+#[only_in(guilds)]
+#[checks(DJ)]
+#[aliases("equalizer")]
+async fn eq(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let (manager, guild, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    if !require_same_channel(ctx, msg, &guild).await {
+        return Ok(());
+    }
+    let mut queue = queue_lock.lock().await;
+    let presets = EqPreset::ALL.iter().map(|preset| format!("`{}`", preset.name())).collect::<Vec<_>>().join(", ");
+
+    if args.is_empty() {
+        respond(ctx, msg, Reply::Equalizer(queue.eq.name(), &presets)).await;
+        return Ok(());
+    }
+
+    let preset = match EqPreset::from_name(args.message().trim()) {
+        Some(preset) => preset,
+        None => {
+            respond(ctx, msg, Reply::UnknownPreset(&presets)).await;
+            return Ok(());
+        }
+    };
+    queue.eq = preset;
+    respond(ctx, msg, Reply::EqualizerSet(preset.name())).await;
+
+    // filters are part of the ffmpeg process, so restart the current song where it left off
+    if let Some(call_lock) = manager.get(guild.id) {
+        let looping = queue.looping.is_some();
+        if let Some(song) = &mut queue.now_playing {
+            let info = match &song.handle {
+                Some(handle) => handle.get_info().await.ok(),
+                None => None,
+            };
+
+            if let (Some(info), Some(handle)) = (info, &song.handle) {
+                let _ = handle.stop();
+                song.start = Some(info.position);
+
+                if !start_song(ctx, msg.channel_id, call_lock, None, song, queue_lock.clone(), preset).await && looping {
+                    // carry over the repeats that were left
+                    if let Some(handle) = &song.handle {
+                        let _ = match info.loops {
+                            LoopState::Infinite => handle.enable_loop(),
+                            LoopState::Finite(count) => handle.loop_for(count),
+                        };
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[command]
+#[aliases("supported")]
+async fn sources(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let mut description = "__Links:__\n".to_owned();
+    for kind in SourceKind::ALL.iter() {
+        description.push_str(&format!("{} - `{}`\n", kind.name(), kind.example_link()));
+    }
+    description.push_str("Other links are opened with youtube-dl if it supports the site\n");
+    description.push_str("\n__Searches:__\nYouTube by default");
+    for kind in SourceKind::ALL.iter() {
+        if let Some(prefix) = kind.search_prefix() {
+            description.push_str(&format!("\n{} - `{}query`", kind.name(), prefix));
+        }
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed.title("Supported sources").description(description);
+    send_embed(ctx, msg.channel_id, embed, None).await?;
+
+    Ok(())
+}
+
+#[command]
+async fn guilds(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let mut description = String::new();
+
+    for guild_id in ctx.cache.guilds().await {
+        let info = ctx.cache.guild_field(guild_id, |guild| (guild.name.clone(), guild.member_count)).await;
+
+        match info {
+            Some((name, member_count)) => description.push_str(&format!("`{}` {} - {} members\n", guild_id, name, member_count)),
+            None => description.push_str(&format!("`{}` (unavailable)\n", guild_id)),
+        }
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed.title("Guilds").description(description);
+    send_embed(ctx, msg.channel_id, embed, None).await?;
+
+    Ok(())
+}
+
+// Pauses every server's song, ex. before restarting the bot for maintenance
+#[command]
+async fn pauseall(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    // each queue is locked on its own, so a busy server can't hold up the others for long
+    let queues = get_queues(ctx).await.map.read().await.values().cloned().collect::<Vec<_>>();
+    let mut paused = 0;
+
+    for queue_lock in queues {
+        let handle = queue_lock.lock().await.now_playing.as_ref().and_then(|song| song.handle.clone());
+        if let Some(handle) = handle {
+            if handle.get_info().await.is_ok_and(|info| info.playing == PlayMode::Play) && handle.pause().is_ok() {
+                paused += 1;
+            }
+        }
+    }
+
+    respond(ctx, msg, Reply::PausedAll(paused)).await;
+    Ok(())
+}
+
+// Stops playback and clears the queue in every server
+#[command]
+async fn stopall(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let queues = get_queues(ctx).await.map.read().await.values().cloned().collect::<Vec<_>>();
+    let mut stopped = 0;
+
+    for queue_lock in queues {
+        let mut queue = queue_lock.lock().await;
+        if queue.now_playing.is_none() && queue.queue.is_empty() {
+            continue;
+        }
+        // the queue is emptied first so SongEndNotifier has nothing to start
+        let handle = queue.now_playing.as_ref().and_then(|song| song.handle.clone());
+        queue.stop();
+        if let Some(handle) = handle {
+            let _ = handle.stop();
+        }
+        update_live_queue(ctx, &queue).await;
+        stopped += 1;
+    }
+
+    respond(ctx, msg, Reply::StoppedAll(stopped)).await;
+    Ok(())
+}
+
+#[command]
+async fn leaveguild(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = match args.single::<u64>() {
+        Ok(id) => GuildId(id),
+        Err(_) => {
+            respond(ctx, msg, Reply::LeaveGuildUsage).await;
+            return Ok(());
+        }
+    };
+
+    let manager = songbird::get(ctx)
+        .await
+        .expect("Songbird Voice client passed in at initialization.")
+        .clone();
+    // not being in a call is fine
+    let _ = manager.remove(guild_id).await;
+
+    guild_id.leave(&ctx.http).await?;
+    respond(ctx, msg, Reply::LeftGuild(guild_id.0)).await;
+    Ok(())
+}
+
+// Saves the current song's audio on the host, so it can be played with "file:<name>" without streaming it again
+#[command]
+#[only_in(guilds)]
+#[aliases("download")]
+async fn savesong(ctx: &Context, msg: &Message, _: Args) -> CommandResult {
+    let (_, _, queue_lock) = match resolve_context(ctx, msg).await {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    let url = match &queue_lock.lock().await.now_playing {
+        Some(Song { source: SongSource::Local { path: _ }, .. }) => {
+            respond(ctx, msg, Reply::AlreadySaved).await;
+            return Ok(());
+        }
+        Some(song) => song.source.url().to_owned(),
+        None => return nothing_playing(ctx, msg).await,
+    };
+
+    match ytdl_download(&*get_ytdl_config(ctx).await, &url).await {
+        Some(path) => {
+            let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+            respond(ctx, msg, Reply::Saved(&name)).await;
+        }
+        None => respond(ctx, msg, Reply::SaveFailed).await,
+    }
+    Ok(())
+}
+
+// Finds the primary name of a command from any of its names
+fn command_name(name: &str) -> Option<&'static str> {
+    GENERAL_GROUP.options.commands
+        .iter()
+        .map(|command| command.options.names)
+        .find(|names| names.iter().any(|other| other.eq_ignore_ascii_case(name)))
+        .map(|names| names[0])
+}
+
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn disable(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+
+    let reply = match command_name(args.message()) {
+        Some("disable") | Some("enable") => Reply::CantDisable,
+        Some(name) => {
+            get_settings(ctx).await.update(guild_id, |settings| settings.disabled_commands.insert(name.to_owned())).await;
+            Reply::Disabled(name)
+        }
+        None => Reply::UnknownCommand(args.message()),
+    };
+
+    respond(ctx, msg, reply).await;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn enable(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+
+    let reply = match command_name(args.message()) {
+        Some(name) => {
+            get_settings(ctx).await.update(guild_id, |settings| settings.disabled_commands.remove(name)).await;
+            Reply::Enabled(name)
+        }
+        None => Reply::UnknownCommand(args.message()),
+    };
+
+    respond(ctx, msg, reply).await;
+    Ok(())
+}
+
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn prefix(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+    let settings = get_settings(ctx).await;
+
+    match args.message().trim() {
+        "" => respond(ctx, msg, Reply::Prefix(&settings.prefix(Some(guild_id)).await)).await,
+        "reset" => {
+            settings.update(guild_id, |settings| settings.prefix = None).await;
+            respond(ctx, msg, Reply::PrefixReset(&settings.prefix(None).await)).await;
+        }
+        new_prefix if new_prefix.contains(char::is_whitespace) => respond(ctx, msg, Reply::PrefixSpaces).await,
+        new_prefix => {
+            settings.update(guild_id, |settings| settings.prefix = Some(new_prefix.to_owned())).await;
+            respond(ctx, msg, Reply::PrefixSet(new_prefix)).await;
+        }
+    }
+
+    Ok(())
+}
+
+// Changes the language of the bot's replies in this server, or shows the current one
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+#[aliases("lang")]
+async fn language(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+    let languages = Locale::ALL.iter().map(|locale| format!("`{}` {}", locale.code(), locale.name())).collect::<Vec<_>>().join(", ");
+
+    if args.is_empty() {
+        let current = locale(ctx, Some(guild_id)).await;
+        respond(ctx, msg, Reply::Language(current.name(), &languages)).await;
+        return Ok(());
+    }
+
+    match Locale::from_name(args.message().trim()) {
+        Some(new_locale) => {
+            get_settings(ctx).await.update(guild_id, |settings| settings.locale = new_locale).await;
+            respond(ctx, msg, Reply::LanguageSet).await;
+        }
+        None => respond(ctx, msg, Reply::UnknownLanguage(&languages)).await,
+    }
+    Ok(())
+}
+
+// Sets whether play joins voice by itself ("on"), needs summon first ("off"), or follows AUTO_JOIN ("reset")
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn autojoin(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+    let settings = get_settings(ctx).await;
+
+    let auto_join = match args.message().trim().to_lowercase().as_str() {
+        "" => {
+            let reply = if settings.auto_join(guild_id).await { Reply::AutoJoinOn } else { Reply::AutoJoinOff };
+            respond(ctx, msg, reply).await;
+            return Ok(());
+        }
+        "on" => Some(true),
+        "off" => Some(false),
+        "reset" => None,
+        _ => {
+            respond(ctx, msg, Reply::AutoJoinUsage).await;
+            return Ok(());
+        }
+    };
+    settings.update(guild_id, |settings| settings.auto_join = auto_join).await;
+
+    let reply = if settings.auto_join(guild_id).await { Reply::AutoJoinOn } else { Reply::AutoJoinOff };
+    respond(ctx, msg, reply).await;
+    Ok(())
+}
+
+// What an on/off settings command replies with
+struct ToggleReplies {
+    on: Reply<'static>,
+    off: Reply<'static>,
+    usage: Reply<'static>,
+}
+
+// Turns a server setting "on" or "off", or shows it when there's no argument
+async fn toggle_setting(ctx: &Context, msg: &Message, args: Args, get: fn(&GuildSettings) -> bool, set: fn(&mut GuildSettings, bool), replies: ToggleReplies) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+    let settings = get_settings(ctx).await;
+
+    let value = match args.message().trim().to_lowercase().as_str() {
+        "" => settings.get(guild_id, get).await,
+        "on" => true,
+        "off" => false,
+        _ => {
+            respond(ctx, msg, replies.usage).await;
+            return Ok(());
+        }
+    };
+    settings.update(guild_id, |settings| set(settings, value)).await;
+
+    respond(ctx, msg, if value { replies.on } else { replies.off }).await;
+    Ok(())
+}
+
+// Sets whether playback commands only work from the bot's voice channel ("on") or from anywhere ("off")
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn samechannel(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let replies = ToggleReplies { on: Reply::SameChannelOn, off: Reply::SameChannelOff, usage: Reply::SameChannelUsage };
+    toggle_setting(ctx, msg, args, |settings| settings.same_channel_only, |settings, value| settings.same_channel_only = value, replies).await
+}
+
+// What a numeric settings command replies with
+struct NumberReplies {
+    current: fn(u64) -> Reply<'static>,
+    usage: Reply<'static>,
+}
+
+// Sets a server setting to a number in range, or shows it when there's no argument
+async fn number_setting(ctx: &Context, msg: &Message, mut args: Args, get: fn(&GuildSettings) -> u64, set: fn(&mut GuildSettings, u64), range: RangeInclusive<u64>, replies: NumberReplies) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+    let settings = get_settings(ctx).await;
+
+    if args.is_empty() {
+        let value = settings.get(guild_id, get).await;
+        respond(ctx, msg, (replies.current)(value)).await;
+        return Ok(());
+    }
+
+    let value = match args.single::<u64>() {
+        Ok(value) if range.contains(&value) => value,
+        _ => {
+            respond(ctx, msg, replies.usage).await;
+            return Ok(());
+        }
+    };
+    settings.update(guild_id, |settings| set(settings, value)).await;
+
+    respond(ctx, msg, (replies.current)(value)).await;
+    Ok(())
+}
+
+// Sets how many seconds a song has to play before non-DJs can skip it, 0 to allow skipping right away
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn skipdelay(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let replies = NumberReplies { current: Reply::SkipDelay, usage: Reply::SkipDelayUsage };
+    number_setting(ctx, msg, args, |settings| settings.skip_delay, |settings, value| settings.skip_delay = value, 0..=600, replies).await
+}
+
+// Sets how many minutes the bot stays in voice with nothing playing, 0 to stay until it's told to leave
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn idletimeout(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+    let settings = get_settings(ctx).await;
+
+    if !args.is_empty() {
+        match args.single::<u64>() {
+            Ok(minutes) if minutes <= 1440 => {
+                settings.update(guild_id, |settings| settings.idle_timeout = Some(minutes)).await;
+            }
+            _ => {
+                respond(ctx, msg, Reply::IdleTimeoutUsage).await;
+                return Ok(());
+            }
+        }
+    }
+
+    match settings.idle_timeout(guild_id).await {
+        Some(timeout) => respond(ctx, msg, Reply::IdleTimeout(timeout.as_secs().div_ceil(60))).await,
+        None => respond(ctx, msg, Reply::IdleTimeoutOff).await,
+    }
+    Ok(())
+}
+
+// Sets how many seconds search waits for a result to be picked
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn selecttimeout(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let replies = NumberReplies { current: Reply::SelectionTimeout, usage: Reply::SelectionTimeoutUsage };
+    number_setting(ctx, msg, args, |settings| settings.selection_timeout, |settings, value| settings.selection_timeout = value, 5..=300, replies).await
+}
+
+// Sets how many seconds have to pass before summon can move the bot to another channel again
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn movecooldown(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let replies = NumberReplies { current: Reply::MoveCooldownSet, usage: Reply::MoveCooldownUsage };
+    number_setting(ctx, msg, args, |settings| settings.move_cooldown, |settings, value| settings.move_cooldown = value, 0..=600, replies).await
+}
+
+// Sends embeds as plain text in the server ("on"), or as embeds again ("off")
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn plaintext(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let replies = ToggleReplies { on: Reply::PlainTextOn, off: Reply::PlainTextOff, usage: Reply::PlainTextUsage };
+    toggle_setting(ctx, msg, args, |settings| settings.plain_text, |settings, value| settings.plain_text = value, replies).await
+}
+
+// Mentions whoever queued a song when it starts ("on"), for people who queue and go idle, or stops ("off")
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn pingrequester(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let replies = ToggleReplies { on: Reply::PingRequesterOn, off: Reply::PingRequesterOff, usage: Reply::PingRequesterUsage };
+    toggle_setting(ctx, msg, args, |settings| settings.ping_requester, |settings, value| settings.ping_requester = value, replies).await
+}
+
+// Confirms skip, stop, pause and resume with a reaction on the command ("on"), or with a message ("off")
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn reactions(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let replies = ToggleReplies { on: Reply::ReactionsOn, off: Reply::ReactionsOff, usage: Reply::ReactionsUsage };
+    toggle_setting(ctx, msg, args, |settings| settings.reactions, |settings, value| settings.reactions = value, replies).await
+}
+
+// Keeps the now playing embed at the bottom of this channel ("on") or stops ("off")
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+#[aliases("sticky")]
+async fn stickynp(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+
+    let channel = match args.message().trim().to_lowercase().as_str() {
+        "on" => Some(msg.channel_id),
+        "off" => None,
+        _ => {
+            respond(ctx, msg, Reply::StickyUsage).await;
+            return Ok(());
+        }
+    };
+    get_settings(ctx).await.update(guild_id, |settings| settings.sticky_channel = channel.map(|channel| channel.0)).await;
+
+    match channel {
+        Some(channel) => respond(ctx, msg, Reply::StickyOn(&channel.mention().to_string())).await,
+        None => respond(ctx, msg, Reply::StickyOff).await,
+    }
+    Ok(())
+}
+
+// Limits music commands to this channel ("on") or lets them be used anywhere ("off")
+#[command]
+#[only_in(guilds)]
+#[required_permissions("MANAGE_GUILD")]
+async fn musicchannel(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let guild_id = msg.guild_id.unwrap();
+
+    let channel = match args.message().trim().to_lowercase().as_str() {
+        "on" => Some(msg.channel_id),
+        "off" => None,
+        _ => {
+            respond(ctx, msg, Reply::CommandChannelUsage).await;
+            return Ok(());
+        }
+    };
+    get_settings(ctx).await.update(guild_id, |settings| settings.command_channel = channel.map(|channel| channel.0)).await;
+
+    match channel {
+        Some(channel) => respond(ctx, msg, Reply::CommandChannelSet(&channel.mention().to_string())).await,
+        None => respond(ctx, msg, Reply::CommandChannelCleared).await,
+    }
+    Ok(())
+}
+
+// Reposts the now playing embed when a message in the sticky channel pushes it up
+async fn repost_now_playing(ctx: &Context, msg: &Message) {
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => return,
+    };
+    let sticky_channel = get_settings(ctx).await.get(guild_id, |settings| settings.sticky_channel).await;
+    if sticky_channel != Some(msg.channel_id.0) || msg.author.id == ctx.cache.current_user_id().await {
+        return;
+    }
+
+    let queue_lock = get_queues(ctx).await.queue_or_create(&guild_id).await;
+    let mut queue = queue_lock.lock().await;
+    if queue.now_playing.is_none() {
+        return;
+    }
+    // busy channels would repost on every message, so wait a bit between reposts
+    if let Some((message_id, posted)) = queue.sticky_message {
+        if posted.elapsed() < STICKY_COOLDOWN {
+            return;
+        }
+        let _ = msg.channel_id.delete_message(&ctx.http, message_id).await;
+    }
+
+    let manager = songbird::get(ctx)
+        .await
+        .expect("Songbird Voice client passed in at initialization.");
+    let connected = manager.get(guild_id).is_some();
+    queue.sticky_message = match send_now_playing(ctx, msg.channel_id, &queue, connected).await {
+        Ok(message) => Some((message.id, Instant::now())),
+        Err(why) => {
+            println!("Error reposting now playing: {:?}", why);
+            None
+        }
+    };
+}
+
+const STICKY_COOLDOWN: Duration = Duration::from_secs(5);
+
+#[hook]
+pub(crate) async fn dynamic_prefix(ctx: &Context, msg: &Message) -> Option<String> {
+    Some(get_settings(ctx).await.prefix(msg.guild_id).await)
+}
+
+// Tells people the prefix when they mention the bot without a command
+#[hook]
+pub(crate) async fn normal_message(ctx: &Context, msg: &Message) {
+    let bot_id = ctx.cache.current_user_id().await;
+    let content = msg.content.trim();
+
+    if content == format!("<@{}>", bot_id) || content == format!("<@!{}>", bot_id) {
+        let prefix = get_settings(ctx).await.prefix(msg.guild_id).await;
+        respond(ctx, msg, Reply::PrefixHint(&prefix)).await;
+    }
+    repost_now_playing(ctx, msg).await;
+}
+
+// Identical commands from the same user and channel this close together are dropped as duplicates
+const DUPLICATE_WINDOW: Duration = Duration::from_secs(1);
+
+pub(crate) struct RecentCommandsKey;
+impl TypeMapKey for RecentCommandsKey {
+    type Value = Arc<Mutex<HashMap<(UserId, ChannelId, String), Instant>>>;
+}
+
+#[hook]
+pub(crate) async fn before(ctx: &Context, msg: &Message, command_name: &str) -> bool {
+    // Discord sometimes delivers the same message twice, which would queue songs twice
+    {
+        let recent_lock = get_recent_commands(ctx).await;
+        let mut recent = recent_lock.lock().await;
+        recent.retain(|_, sent| sent.elapsed() < DUPLICATE_WINDOW);
+        if recent.insert((msg.author.id, msg.channel_id, msg.content.clone()), Instant::now()).is_some() {
+            return false;
+        }
+    }
+
+    if let Some(guild_id) = msg.guild_id {
+        let settings = get_settings(ctx).await;
+        let disabled = settings.get(guild_id, |settings| settings.disabled_commands.contains(command_name)).await;
+
+        if disabled {
+            respond(ctx, msg, Reply::CommandDisabled).await;
+            return false;
+        }
+
+        // musicchannel itself works anywhere so the channel can be moved
+        let command_channel = settings.get(guild_id, |settings| settings.command_channel).await;
+        if let Some(channel) = command_channel.map(ChannelId) {
+            if channel != msg.channel_id && command_name != "musicchannel" {
+                respond(ctx, msg, Reply::CommandChannelOnly(&channel.mention().to_string())).await;
+                return false;
+            }
+        }
+
+        // any command counts as activity, so an idle bot gets the full timeout again
+        let queue_lock = get_queues(ctx).await.queue_or_create(&guild_id).await;
+        let mut queue = queue_lock.lock().await;
+        if queue.idle_since.is_some() {
+            queue.idle_since = Some(Instant::now());
+        }
+    }
+
+    true
+}
+
+#[hook]
+pub(crate) async fn dispatch_error(ctx: &Context, msg: &Message, error: DispatchError) {
+    match error {
+        DispatchError::CheckFailed(_, Reason::User(reason)) => {
+            check_msg(msg.channel_id.say(&ctx.http, reason).await);
+        }
+        DispatchError::LackingPermissions(permissions) => {
+            respond(ctx, msg, Reply::MissingPermission(&permissions.to_string())).await;
+        }
+        DispatchError::OnlyForOwners => {
+            respond(ctx, msg, Reply::OwnersOnly).await;
+        }
+        DispatchError::Ratelimited(_) => {
+            respond(ctx, msg, Reply::Busy).await;
+        }
+        _ => {}
+    }
+}
+
+#[hook]
+pub(crate) async fn after(ctx: &Context, msg: &Message, command_name: &str, command_result: CommandResult) {
+    if let Err(why) = command_result {
+        println!(
+            "Command '{}' returned error {:?} => {}",
+            command_name, why, why
+        );
+    }
+    // after the command so the embed ends up under its reply, now playing already posted one
+    if command_name != "now_playing" {
+        repost_now_playing(ctx, msg).await;
+    }
+}
+
+#[help]
+#[command_not_found_text = "Could not find: `{}`."]
+#[max_levenshtein_distance(3)]
+#[individual_command_tip=""]
+#[strikethrough_commands_tip_in_guild=""]
+async fn my_help(
+    ctx: &Context,
+    msg: &Message,
+    args: Args,
+    help_options: &'static HelpOptions,
+    groups: &[&'static CommandGroup],
+    owners: HashSet<UserId>,
+) -> CommandResult {
+    if use_embeds(ctx, msg.channel_id).await {
+        let _ = help_commands::with_embeds(ctx, msg, args, help_options, groups, owners).await;
+    } else {
+        let _ = help_commands::plain(ctx, msg, args, help_options, groups, owners).await;
+    }
+    Ok(())
+}
+
+// Sends a reply in the guild's language
+async fn say(ctx: &Context, channel_id: ChannelId, guild_id: Option<GuildId>, reply: Reply<'_>) {
+    let text = reply.render(locale(ctx, guild_id).await);
+    check_msg(channel_id.say(&ctx.http, text).await);
+}
+
+// Sends an embed, or the same text without one where the server turned embeds off with
+// plaintext or the bot lacks Embed Links, which Discord would otherwise refuse
+async fn send_embed(ctx: &Context, channel_id: ChannelId, embed: CreateEmbed, components: Option<CreateComponents>) -> Result<Message> {
+    let plain = !use_embeds(ctx, channel_id).await;
+
+    channel_id.send_message(&ctx.http, |m| {
+        if plain {
+            m.content(embed_text(&embed));
+        } else {
+            m.set_embed(embed);
+        }
+        if let Some(components) = components {
+            m.set_components(components);
+        }
+        m
+    }).await
+}
+
+// Discord's error codes for a deleted channel or message
+const UNKNOWN_CHANNEL: isize = 10003;
+const UNKNOWN_MESSAGE: isize = 10008;
+
+// Whether a request failed because the message or its channel no longer exists
+fn is_deleted(error: &SerenityError) -> bool {
+    match error {
+        SerenityError::Http(http_error) => match &**http_error {
+            HttpError::UnsuccessfulRequest(response) => matches!(response.error.code, UNKNOWN_CHANNEL | UNKNOWN_MESSAGE),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// Replaces a message sent by send_embed, switching between text and embed if the setting changed
+async fn edit_embed(ctx: &Context, channel_id: ChannelId, message_id: MessageId, embed: CreateEmbed) -> Result<Message> {
+    let plain = !use_embeds(ctx, channel_id).await;
+
+    channel_id.edit_message(&ctx.http, message_id, |m| {
+        if plain {
+            m.content(embed_text(&embed)).set_embeds(Vec::new())
+        } else {
+            m.content("").set_embed(embed)
+        }
+    }).await
+}
+
+async fn use_embeds(ctx: &Context, channel_id: ChannelId) -> bool {
+    // DMs always allow embeds
+    let channel = match ctx.cache.guild_channel(channel_id).await {
+        Some(channel) => channel,
+        None => return true,
+    };
+    if get_settings(ctx).await.get(channel.guild_id, |settings| settings.plain_text).await {
+        return false;
+    }
+    let bot_id = ctx.cache.current_user_id().await;
+    channel.permissions_for_user(&ctx.cache, bot_id).await.is_ok_and(|permissions| permissions.embed_links())
+}
+
+// The title, description, fields and footer of an embed as message text
+fn embed_text(embed: &CreateEmbed) -> String {
+    let text_at = |key: &str, inner: &str| embed.0.get(key).and_then(|value| value.get(inner)).and_then(Value::as_str);
+    let mut lines = Vec::new();
+
+    if let Some(title) = text_at("author", "name").or_else(|| embed.0.get("title").and_then(Value::as_str)) {
+        lines.push(format!("**{}**", title));
+    }
+    if let Some(description) = embed.0.get("description").and_then(Value::as_str) {
+        lines.push(description.to_owned());
+    }
+    for field in embed.0.get("fields").and_then(Value::as_array).into_iter().flatten() {
+        let name = field.get("name").and_then(Value::as_str).unwrap_or_default();
+        let value = field.get("value").and_then(Value::as_str).unwrap_or_default();
+        lines.push(format!("**{}:** {}", name, value));
+    }
+    if let Some(footer) = text_at("footer", "text") {
+        lines.push(format!("*{}*", footer));
+    }
+
+    // the most a message can hold
+    truncate(&lines.join("\n"), 2000)
+}
+
+// Replies in the channel the command came from
+async fn respond(ctx: &Context, msg: &Message, reply: Reply<'_>) {
+    // a reaction on the command keeps the channel clear, the message is sent if the bot can't react
+    if let (Some(emoji), Some(guild_id)) = (reply.reaction(), msg.guild_id) {
+        if get_settings(ctx).await.get(guild_id, |settings| settings.reactions).await && msg.react(&ctx.http, emoji).await.is_ok() {
+            return;
+        }
+    }
+    say(ctx, msg.channel_id, msg.guild_id, reply).await;
+}
+
+// DMs always use English
+async fn locale(ctx: &Context, guild_id: Option<GuildId>) -> Locale {
+    match guild_id {
+        Some(guild_id) => get_settings(ctx).await.get(guild_id, |settings| settings.locale).await,
+        None => Locale::default(),
+    }
+}
+
+fn check_msg(result: Result<Message>) {
+    if let Err(why) = result {
+        println!("Error sending message: {:?}", why);
+    }
+}
+
+// Resolves a query or link into the songs to queue, which is empty if nothing matched
+// Why a query couldn't be looked up. Each is told to the user instead of "No matches"
+enum LookupError {
+    QuotaExceeded,
+    // Spotify albums and playlists, only tracks can be matched on YouTube
+    SpotifyCollection,
+    // a track link Spotify has nothing for, ex. removed or not released in the bot's market
+    SpotifyUnavailable,
+}
+
+impl From<QuotaExceeded> for LookupError {
+    fn from(_: QuotaExceeded) -> LookupError {
+        LookupError::QuotaExceeded
+    }
+}
+
+impl LookupError {
+    fn reply(&self) -> Reply<'static> {
+        match self {
+            LookupError::QuotaExceeded => Reply::QuotaExceeded,
+            LookupError::SpotifyCollection => Reply::SpotifyCollection,
+            LookupError::SpotifyUnavailable => Reply::SpotifyUnavailable,
+        }
+    }
+}
+
+async fn get_songs(ctx: &Context, msg: &Message, message: &str) -> std::result::Result<SongBatch, LookupError> {
+    // audio files uploaded along with the command
+    let attachments = msg.attachments
+        .iter()
+        .filter(|attachment| attachment.content_type.as_deref().is_some_and(|content_type| content_type.starts_with("audio/")))
+        .collect::<Vec<_>>();
+    if !attachments.is_empty() {
+        let mut songs = Vec::new();
+
+        for attachment in attachments {
+            let duration = ffprobe_duration(&attachment.url).await.unwrap_or_default();
+            songs.push(Song::new(attachment.filename.clone(), msg.author.name.clone(), msg.author.tag(), duration, SongSource::Direct { url: attachment.url.clone() }));
+        }
+        return Ok(songs.into());
+    }
+
+    let spotify_link = spotify_uri_link(message);
+    let message = spotify_link.as_deref().unwrap_or(message);
+
+    if message.starts_with("http") && matches!(SourceKind::from_link(message), Some(SourceKind::YouTube)) {
+        if let Some(playlist_id) = message.split(&['?', '&'][..]).find_map(|param| param.strip_prefix("list=")) {
+            // Mixes are generated endlessly, so play the linked video and let autoplay continue instead
+            if playlist_id.starts_with("RD") {
+                let song = get_song(ctx, msg, message).await?;
+                // autoplay only turns on once there's a video for it to continue from
+                if let (Some(_), Some(guild_id)) = (&song, msg.guild_id) {
+                    get_queues(ctx).await.queue_or_create(&guild_id).await.lock().await.autoplay = true;
+                    respond(ctx, msg, Reply::MixUnsupported).await;
+                }
+                return Ok(song.into_iter().collect::<Vec<_>>().into());
+            }
+
+            let mut playlist = get_search_provider(ctx).await.playlist(playlist_id).await?;
+
+            if playlist.total > get_api_access(ctx).await.max_playlist_size {
+                respond(ctx, msg, Reply::PlaylistLimit(playlist.songs.len(), playlist.total)).await;
+            }
+            for song in &mut playlist.songs {
+                song.author = msg.author.tag();
+            }
+            return Ok(SongBatch { songs: playlist.songs, playlist_name: playlist.name });
+        }
+    }
+
+    if message.starts_with("http") && matches!(SourceKind::from_link(message), Some(SourceKind::Bandcamp)) && message.contains("/album/") {
+        let max = get_api_access(ctx).await.max_playlist_size;
+        let tracks = ytdl_playlist_info(&*get_ytdl_config(ctx).await, message, max).await;
+
+        // youtube-dl stops at the limit without saying how long the album is
+        if tracks.len() == max {
+            respond(ctx, msg, Reply::AlbumLimit(max)).await;
+        }
+        return Ok(SongBatch {
+            songs: tracks.iter().map(|track| track.as_song(msg.author.tag())).collect(),
+            playlist_name: tracks.first().and_then(|track| track.playlist_title.clone()),
+        });
+    }
+
+    Ok(get_song(ctx, msg, message).await?.into_iter().collect::<Vec<_>>().into())
+}
+
+// Resolves one query to a song, counting how often each kind of query finds something
+async fn get_song(ctx: &Context, msg: &Message, message: &str) -> std::result::Result<Option<Song>, LookupError> {
+    let song = lookup_song(ctx, msg, message).await;
+    match &song {
+        Ok(song) => get_source_stats(ctx).await.record_lookup(query_source(message), song.is_some()),
+        Err(LookupError::SpotifyUnavailable) => get_source_stats(ctx).await.record_lookup(query_source(message), false),
+        Err(_) => {}
+    }
+    song
+}
+
+// What kind of lookup a query makes, for the source stats
+fn query_source(message: &str) -> &'static str {
+    let prefixed = SourceKind::ALL.iter().find(|kind| kind.search_prefix().is_some_and(|prefix| message.starts_with(prefix)));
+
+    match prefixed {
+        Some(SourceKind::SoundCloud) => "SoundCloud search",
+        Some(_) => "Search",
+        None if message.starts_with("file:") => "Local",
+        None if message.starts_with("http") => SourceKind::from_link(message).map_or("Extractor", |kind| kind.name()),
+        None => "Search",
+    }
+}
+
+// Searches fail when the YouTube API is out of quota, links are still looked up with youtube-dl
+async fn lookup_song(ctx: &Context, msg: &Message, message: &str) -> std::result::Result<Option<Song>, LookupError> {
+    // An explicit source prefix overrides the auto-detection below
+    let prefixed = SourceKind::ALL.iter().find_map(|kind| {
+        kind.search_prefix()
+            .and_then(|prefix| message.strip_prefix(prefix))
+            .map(|query| (kind, query.trim()))
+    });
+
+    if let Some(name) = message.strip_prefix("file:") {
+        return Ok(local_song(ctx, msg, name.trim()).await);
+    }
+
+    match prefixed {
+        Some((SourceKind::SoundCloud, query)) => {
+            return Ok(ytdl_info(&*get_ytdl_config(ctx).await, &format!("scsearch1:{}", query))
+                .await
+                .map(|info| info.as_song(msg.author.tag())));
+        }
+        Some((SourceKind::YouTube, query)) => {
+            return Ok(first_search_result(ctx, query, msg.author.tag()).await?);
+        }
+        _ => {}
+    }
+
+    // a pasted video id, ex. "dQw4w9WgXcQ". Words that happen to look like one fall through to the search
+    if is_video_id(message) {
+        if let Some(mut song) = get_search_provider(ctx).await.video(message).await? {
+            song.author = msg.author.tag();
+            return Ok(Some(song));
+        }
+    }
+
+    if message.starts_with("http") && (message.contains("spotify.com/album/") || message.contains("spotify.com/playlist/")) {
+        return Err(LookupError::SpotifyCollection);
+    }
+
+    if message.starts_with("http") {
+        let kind = match SourceKind::from_link(message) {
+            Some(kind) => kind,
+            // youtube-dl supports hundreds of other sites, search for the link if it can't read it either
+            None => {
+                return match ytdl_info(&*get_ytdl_config(ctx).await, message).await {
+                    Some(info) => Ok(Some(info.as_song(msg.author.tag()))),
+                    None => Ok(first_search_result(ctx, message, msg.author.tag()).await?),
+                };
+            }
+        };
+
+        match kind {
+            SourceKind::Spotify => {
+                let api_access = get_api_access(ctx).await.clone();
+                let track_id = match message.split("track/").nth(1).and_then(|id| id.get(.. 22)) {
+                    Some(track_id) => track_id,
+                    None => return Ok(None),
+                };
+                let track = match api_access.get_spotify_track(track_id).await {
+                    Some(track) => track,
+                    None => return Err(LookupError::SpotifyUnavailable),
+                };
+
+                Ok(spotify_song(ctx, &api_access, track, msg.author.tag()).await?)
+            }
+            SourceKind::SoundCloud | SourceKind::Bandcamp => {
+                Ok(ytdl_info(&*get_ytdl_config(ctx).await, message)
+                    .await
+                    .map(|info| info.as_song(msg.author.tag())))
+            }
+            SourceKind::YouTube => {
+                let link = message.to_owned();
+                let id = match link.split("?v=").nth(1).and_then(|id| id.get(.. 11)) {
+                    Some(id) => id,
+                    None => return Ok(None),
+                };
+                let mut song = match get_search_provider(ctx).await.video(id).await {
+                    Ok(Some(song)) => song,
+                    Ok(None) => return Ok(None),
+                    // youtube-dl can still read the link without the API
+                    Err(QuotaExceeded) => ytdl_info(&*get_ytdl_config(ctx).await, &link)
+                        .await
+                        .map(|info| info.as_song(String::new()))
+                        .ok_or(QuotaExceeded)?,
+                };
+                song.author = msg.author.tag();
+
+                // timestamped links, ex. "&t=1m15s", and clips, ex. "&start=30&end=75"
+                song.start = link.split(&['?', '&'][..])
+                    .find_map(|param| param.strip_prefix("t=").or_else(|| param.strip_prefix("start=")))
+                    .and_then(parse_timestamp)
+                    .filter(|start| !start.is_zero());
+                song.end = link.split(&['?', '&'][..])
+                    .find_map(|param| param.strip_prefix("end="))
+                    .and_then(parse_timestamp)
+                    .filter(|end| *end > song.start.unwrap_or_default() && *end < song.duration);
+
+                Ok(Some(song))
+            }
+        }
+    } else {
+        Ok(first_search_result(ctx, message, msg.author.tag()).await?)
+    }
+}
+
+// A file saved by savesong, names can't leave the download directory
+async fn local_song(ctx: &Context, msg: &Message, name: &str) -> Option<Song> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.contains("..") {
+        return None;
+    }
+    let path = get_ytdl_config(ctx).await.download_dir.join(name);
+    if !path.is_file() {
+        return None;
+    }
+    let path = path.to_string_lossy().into_owned();
+
+    let title = name.rsplit_once('.').map_or(name, |(stem, _)| stem).to_owned();
+    let duration = ffprobe_duration(&path).await.unwrap_or_default();
+    Some(Song::new(title, "Saved file".to_owned(), msg.author.tag(), duration, SongSource::Local { path }))
+}
+
+fn is_video_id(text: &str) -> bool {
+    text.len() == 11 && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+// Spotify URIs like "spotify:track:ID" as their open.spotify.com link, so they're handled like pasted links
+fn spotify_uri_link(uri: &str) -> Option<String> {
+    let mut parts = uri.strip_prefix("spotify:")?.split(':');
+    let kind = parts.next().filter(|kind| matches!(*kind, "track" | "album" | "playlist"))?;
+    let id = parts.next().filter(|id| !id.is_empty())?;
+
+    Some(format!("https://open.spotify.com/{}/{}", kind, id))
+}
+
+// The YouTube search used to find a Spotify track. "explicit" is only added for explicit
+// tracks so clean tracks don't match the explicit upload.
+fn spotify_search_query(api_access: &ApiAccess, track: &SpotifyTrack) -> String {
+    let mut query = format!("{} {}", track.name, track.artists.first().map_or("", |artist| &artist.name));
+    if track.explicit {
+        query.push_str(" explicit");
+    }
+    if !api_access.spotify_search_terms.is_empty() {
+        query.push(' ');
+        query.push_str(&api_access.spotify_search_terms);
+    }
+    query
+}
+
+// Finds a Spotify track on YouTube, reusing the match from last time if there was one
+async fn spotify_song(ctx: &Context, api_access: &ApiAccess, track: SpotifyTrack, author: String) -> std::result::Result<Option<Song>, QuotaExceeded> {
+    let cache = get_spotify_cache(ctx).await;
+
+    let mut song = match cache.get(&track.id).await {
+        Some(spotify_match) => spotify_match.as_song(author),
+        None => {
+            let song = match first_search_result(ctx, &spotify_search_query(api_access, &track), author).await? {
+                Some(song) => song,
+                None => return Ok(None),
+            };
+            cache.insert(track.id.clone(), SpotifyMatch::from_song(&song)).await;
+            song
+        }
+    };
+    song.thumbnail_url = track.album.images.first().map(|image| image.url.clone());
+    song.spotify_id = Some(track.id);
+
+    Ok(Some(song))
+}
+
+// Best match from the configured search provider
+async fn first_search_result(ctx: &Context, query: &str, author: String) -> std::result::Result<Option<Song>, QuotaExceeded> {
+    let mut results = get_search_provider(ctx).await.search(query).await?
+        .into_iter()
+        .take(1 + MAX_ALTERNATES)
+        .map(|song| Song { author: author.clone(), ..song });
+    let song = results.next();
+
+    Ok(song.map(|song| Song { alternates: results.collect(), ..song }))
+}
+
+async fn get_search_provider(ctx: &Context) -> Arc<dyn SearchProvider> {
+    ctx.data.read().await.get::<SearchProviderKey>().cloned().expect("SearchProvider not yet initialized")
+}
+
+async fn get_spotify_cache(ctx: &Context) -> Arc<SpotifyCache> {
+    ctx.data.read().await.get::<SpotifyCacheKey>().cloned().expect("Spotify cache not yet initialized")
+}
+
+async fn get_api_access(ctx: &Context) -> Arc<ApiAccess> {
+    ctx.data.read().await.get::<ApiAccessKey>().cloned().expect("API Access not yet initialized")
+}
+
+async fn get_ytdl_config(ctx: &Context) -> Arc<YtdlConfig> {
+    ctx.data.read().await.get::<YtdlConfigKey>().cloned().expect("YtdlConfig not yet initialized")
+}
+
+async fn get_settings(ctx: &Context) -> Arc<GuildSettingsStore> {
+    ctx.data.read().await.get::<GuildSettingsKey>().cloned().expect("GuildSettingsStore not yet initialized")
+}
+
+async fn get_recent_commands(ctx: &Context) -> Arc<Mutex<HashMap<(UserId, ChannelId, String), Instant>>> {
+    ctx.data.read().await.get::<RecentCommandsKey>().cloned().expect("Recent commands not yet initialized")
+}
+
+async fn get_search_history(ctx: &Context) -> Arc<Mutex<HashMap<UserId, VecDeque<String>>>> {
+    ctx.data.read().await.get::<SearchHistoryKey>().cloned().expect("Search history not yet initialized")
+}
+
+async fn get_queue_pages(ctx: &Context) -> Arc<RwLock<HashMap<MessageId, QueuePage>>> {
+    ctx.data.read().await.get::<QueuePagesKey>().cloned().expect("Queue pages not yet initialized")
+}
+
+async fn get_source_stats(ctx: &Context) -> Arc<SourceStats> {
+    ctx.data.read().await.get::<SourceStatsKey>().cloned().expect("SourceStats not yet initialized")
 }
 
 async fn get_queues(ctx: &Context) -> Arc<PerServerQueue> {
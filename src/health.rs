@@ -0,0 +1,51 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use crate::ApiAccess;
+use crate::stats::SourceStats;
+
+// Answers every HTTP request with 200 while the gateway is connected and the Spotify
+// token is valid, otherwise 503, so Docker or Kubernetes can restart a wedged bot.
+// GET /metrics answers with the source stats instead.
+pub(crate) async fn serve(port: u16, connected: Arc<AtomicBool>, api_access: Arc<ApiAccess>, stats: Arc<SourceStats>) {
+    let listener = match TcpListener::bind(("0.0.0.0", port)).await {
+        Ok(listener) => listener,
+        Err(why) => {
+            println!("Could not start health endpoint on port {}: {:?}", port, why);
+            return;
+        }
+    };
+
+    loop {
+        let (mut stream, _) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(why) => {
+                println!("Error accepting health check: {:?}", why);
+                continue;
+            }
+        };
+
+        let healthy = connected.load(Ordering::Relaxed) && api_access.spotify_token_valid().await;
+        let stats = stats.clone();
+        tokio::spawn(async move {
+            // only the path matters, but read the whole request so the client sees a clean response
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).await;
+
+            let response = if request.starts_with(b"GET /metrics") {
+                let metrics = stats.render();
+                format!("HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", metrics.len(), metrics)
+            } else if healthy {
+                "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nOK".to_owned()
+            } else {
+                "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 11\r\nConnection: close\r\n\r\nUnavailable".to_owned()
+            };
+            if let Err(why) = stream.write_all(response.as_bytes()).await {
+                println!("Error answering health check: {:?}", why);
+            }
+        });
+    }
+}
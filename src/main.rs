@@ -1,31 +1,93 @@
 mod commands;
+mod health;
+mod messages;
+mod resume;
+mod search;
+mod settings;
+mod spotify_cache;
+mod stats;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::collections::VecDeque;
 use std::env;
+use std::path::PathBuf;
+use std::process::Stdio;
 use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
-use songbird::tracks::TrackHandle;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use songbird::tracks::{LoopState, TrackHandle};
 use tokio::sync::RwLock;
 
-use serde::Deserialize;
-use serenity::model::id::GuildId;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serenity::model::id::{ChannelId, GuildId, MessageId, UserId};
+use serenity::model::voice::VoiceState;
 use serenity::prelude::*;
 use serenity::{async_trait};
+use serenity::gateway::ConnectionStage;
 
 use serenity::framework::standard::{StandardFramework};
+use serenity::framework::standard::buckets::LimitedFor;
+use serenity::client::bridge::gateway::event::ShardStageUpdateEvent;
 use serenity::model::gateway::Ready;
-use songbird::input::Input;
+use serenity::model::interactions::Interaction;
+use serenity::http::Http;
+use songbird::input::{children_to_reader, Codec, Container, Input, Metadata, Restartable};
+use songbird::input::restartable::Restart;
 use songbird::{SerenityInit}; // type alias to not conflict with serenity
 use serenity::Client as SerenityClient;
 use reqwest::Client;
 
-struct Handler;
+use commands::{QueuePagesKey, RecentCommandsKey, SearchHistoryKey};
+use search::{InvidiousSearch, QuotaExceeded, SearchProvider, SearchProviderKey, YouTubeSearch};
+use settings::{GuildSettingsKey, GuildSettingsStore};
+use spotify_cache::{SpotifyCache, SpotifyCacheKey};
+use stats::{SourceStats, SourceStatsKey};
+
+struct Handler {
+    // activities the bot cycles through, empty to leave it unset
+    statuses: Vec<String>,
+    status_interval: Duration,
+    // set when queues are saved and resumed after a restart
+    resume_path: Option<PathBuf>,
+    // ready fires again after reconnecting, the background tasks should only start once
+    tasks_started: AtomicBool,
+    // reported by the health endpoint
+    connected: Arc<AtomicBool>,
+}
+
 #[async_trait]
 impl EventHandler for Handler {
-    async fn ready(&self, _: Context, ready: Ready) {
+    async fn ready(&self, ctx: Context, ready: Ready) {
         println!("{} is connected!", ready.user.name);
+        self.connected.store(true, Ordering::Relaxed);
+
+        if !self.tasks_started.swap(true, Ordering::Relaxed) {
+            if !self.statuses.is_empty() {
+                tokio::spawn(commands::rotate_status(ctx.clone(), self.statuses.clone(), self.status_interval));
+            }
+            if let Some(path) = &self.resume_path {
+                tokio::spawn(commands::resume_queues(ctx.clone(), path.clone()));
+            }
+            tokio::spawn(commands::disconnect_idle(ctx));
+        }
+    }
+
+    async fn shard_stage_update(&self, _: Context, event: ShardStageUpdateEvent) {
+        self.connected.store(event.new == ConnectionStage::Connected, Ordering::Relaxed);
+    }
+
+    async fn voice_state_update(&self, ctx: Context, guild_id: Option<GuildId>, _: Option<VoiceState>, _: VoiceState) {
+        if let Some(guild_id) = guild_id {
+            commands::pause_when_alone(&ctx, guild_id).await;
+        }
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::MessageComponent(component) = interaction {
+            commands::queue_button(&ctx, &component).await;
+        }
     }
 }
 
@@ -34,28 +96,138 @@ async fn main() {
     // get environment vars to run the bot
     let discord_token = env::var("DISCORD_TOKEN").expect("Missing Discord bot token");
     let prefix = env::var("COMMAND_PREFIX").expect("Missing bot prefix");
-    let youtube_key = env::var("YOUTUBE_KEY").expect("Missing YouTube API key");
+    let search_provider_name = env::var("SEARCH_PROVIDER").unwrap_or_else(|_| "youtube".to_owned());
+    // Invidious doesn't need a YouTube API key
+    let youtube_key = match search_provider_name.as_str() {
+        "youtube" => env::var("YOUTUBE_KEY").expect("Missing YouTube API key"),
+        _ => env::var("YOUTUBE_KEY").unwrap_or_default(),
+    };
     let spotify_id = env::var("SPOTIFY_CLIENT_ID").expect("Missing Spotify Client ID");
     let spotify_secret = env::var("SPOTIFY_CLIENT_SECRET").expect("Missing Spotify Client secret");
+    let spotify_search_terms = env::var("SPOTIFY_SEARCH_TERMS").unwrap_or_default();
+    // optional, ex. "JP" and "ja" to prefer Japanese search results
+    let youtube_filters = YouTubeFilters {
+        region: env::var("YOUTUBE_REGION").ok(),
+        language: env::var("YOUTUBE_LANGUAGE").ok(),
+        // left unset, YouTube uses moderate
+        safe_search: env::var("YOUTUBE_SAFE_SEARCH").ok().map(|safe_search| {
+            let safe_search = safe_search.to_lowercase();
+            assert!(matches!(safe_search.as_str(), "none" | "moderate" | "strict"), "YOUTUBE_SAFE_SEARCH must be none, moderate or strict");
+            safe_search
+        }),
+    };
+    let ytdl_config = YtdlConfig {
+        command: env::var("YTDL_COMMAND").unwrap_or_else(|_| "youtube-dl".to_owned()),
+        format: env::var("YTDL_FORMAT").unwrap_or_else(|_| "webm[abr>0]/bestaudio/best".to_owned()),
+        extra_args: env::var("YTDL_ARGS").map(|args| args.split_whitespace().map(str::to_owned).collect()).unwrap_or_default(),
+        download_dir: env::var("DOWNLOAD_DIR").unwrap_or_else(|_| "downloads".to_owned()).into(),
+    };
+    let max_playlist_size = env::var("MAX_PLAYLIST_SIZE")
+        .map(|size| size.parse::<usize>().expect("MAX_PLAYLIST_SIZE must be a number"))
+        .unwrap_or(200);
+    // longer queues are summarized by the queue command instead of listed
+    let queue_summary_size = env::var("QUEUE_SUMMARY_SIZE")
+        .map(|size| size.parse::<usize>().expect("QUEUE_SUMMARY_SIZE must be a number"))
+        .unwrap_or(1000);
+    let max_voice_connections = env::var("MAX_VOICE_CONNECTIONS")
+        .ok()
+        .map(|max| max.parse::<usize>().expect("MAX_VOICE_CONNECTIONS must be a number"));
+    // user ids of the bot's other deployments (ex. a staging copy), which it won't join a channel with
+    let other_instances = env::var("OTHER_INSTANCE_IDS")
+        .map(|ids| ids.split(',').map(str::trim).filter(|id| !id.is_empty()).map(|id| UserId(id.parse().expect("OTHER_INSTANCE_IDS must be user ids separated by commas"))).collect())
+        .unwrap_or_default();
+    let settings_path = env::var("SETTINGS_PATH").unwrap_or_else(|_| "settings.json".to_owned());
+    let spotify_cache_path = env::var("SPOTIFY_CACHE_PATH").unwrap_or_else(|_| "spotify_cache.json".to_owned());
+    // shared by all servers to protect the daily YouTube API quota
+    let youtube_commands_per_second = env::var("YOUTUBE_COMMANDS_PER_SECOND")
+        .map(|limit| limit.parse::<u32>().expect("YOUTUBE_COMMANDS_PER_SECOND must be a number"))
+        .unwrap_or(2);
+    // how long the bot stays in a voice channel with nothing playing, servers can change it with idletimeout
+    let idle_timeout = env::var("IDLE_TIMEOUT")
+        .map(|timeout| Duration::from_secs(timeout.parse::<u64>().expect("IDLE_TIMEOUT must be a number")))
+        .unwrap_or(Duration::from_secs(300));
+    // whether play joins voice by itself when the bot hasn't been summoned
+    let auto_join = env::var("AUTO_JOIN")
+        .map(|auto_join| auto_join.parse::<bool>().expect("AUTO_JOIN must be true or false"))
+        .unwrap_or(true);
+    // ex. "{prefix}help|{servers} servers|{song}"
+    let statuses = env::var("STATUS_MESSAGES")
+        .map(|statuses| statuses.split('|').map(str::trim).filter(|status| !status.is_empty()).map(str::to_owned).collect())
+        .unwrap_or_default();
+    let status_interval = env::var("STATUS_INTERVAL")
+        .map(|interval| Duration::from_secs(interval.parse::<u64>().expect("STATUS_INTERVAL must be a number")))
+        .unwrap_or(Duration::from_secs(60));
+    // rejoin and keep playing after a restart
+    let auto_resume = env::var("AUTO_RESUME")
+        .map(|auto_resume| auto_resume.parse::<bool>().expect("AUTO_RESUME must be true or false"))
+        .unwrap_or(false);
+    let resume_path = auto_resume.then(|| env::var("RESUME_PATH").unwrap_or_else(|_| "resume.json".to_owned()).into());
+    // optional, serves /health for container orchestration
+    let health_port = env::var("HEALTH_PORT")
+        .ok()
+        .map(|port| port.parse::<u16>().expect("HEALTH_PORT must be a port number"));
+
+    // owners can use the owner commands and bypass checks like DJ
+    let mut owners = HashSet::new();
+    match Http::new_with_token(&discord_token).get_current_application_info().await {
+        Ok(info) => match info.team {
+            Some(team) => owners.extend(team.members.iter().map(|member| member.user.id)),
+            None => { owners.insert(info.owner.id); }
+        },
+        Err(why) => println!("Could not access application info: {:?}", why),
+    }
 
     let framework = StandardFramework::new()
         .configure(|c| 
             c
-                .prefix(prefix)
+                // prefixes come from the settings store so guilds can change theirs
+                .prefix("")
+                .dynamic_prefix(commands::dynamic_prefix)
                 .case_insensitivity(true)
+                .owners(owners)
         )
+        .bucket("youtube", |b| b.limit_for(LimitedFor::Global).time_span(1).limit(youtube_commands_per_second)).await
+        .before(commands::before)
+        .normal_message(commands::normal_message)
         .after(commands::after)
+        .on_dispatch_error(commands::dispatch_error)
         .help(&commands::MY_HELP)
-        .group(&commands::GENERAL_GROUP); // refers to general struct
+        .group(&commands::GENERAL_GROUP) // refers to general struct
+        .group(&commands::OWNER_GROUP);
+
+    let api_access = Arc::new(ApiAccess::new(youtube_key, youtube_filters, spotify_id, spotify_secret, max_playlist_size, spotify_search_terms));
+    let token_lifetime = api_access.refresh_spotify_token().await.expect("Failed to generate Spotify token");
+    tokio::spawn(api_access.clone().keep_spotify_token_fresh(token_lifetime));
+    let search_provider: Arc<dyn SearchProvider> = match search_provider_name.as_str() {
+        "youtube" => Arc::new(YouTubeSearch { api_access: api_access.clone() }),
+        "invidious" => Arc::new(InvidiousSearch {
+            base_url: env::var("INVIDIOUS_URL").expect("Missing Invidious instance URL").trim_end_matches('/').to_owned(),
+            http: Client::new(),
+            max_playlist_size,
+        }),
+        other => panic!("Unknown SEARCH_PROVIDER: {}", other),
+    };
 
-    let api_access = ApiAccess::new(youtube_key, spotify_id, spotify_secret).await;
+    let connected = Arc::new(AtomicBool::new(false));
+    let source_stats = Arc::new(SourceStats::default());
+    if let Some(port) = health_port {
+        tokio::spawn(health::serve(port, connected.clone(), api_access.clone(), source_stats.clone()));
+    }
 
     // let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
     let mut client = SerenityClient::builder(discord_token)
-        .event_handler(Handler)
+        .event_handler(Handler { statuses, status_interval, resume_path, tasks_started: AtomicBool::new(false), connected })
         .register_songbird()
-        .type_map_insert::<ApiAccessKey>(Arc::new(api_access))
-        .type_map_insert::<PerServerQueueAccessKey>(Arc::new(PerServerQueue { map: RwLock::new(HashMap::new()) }))
+        .type_map_insert::<ApiAccessKey>(api_access)
+        .type_map_insert::<SearchProviderKey>(search_provider)
+        .type_map_insert::<PerServerQueueAccessKey>(Arc::new(PerServerQueue { map: RwLock::new(HashMap::new()), summary_size: queue_summary_size, max_voice_connections, other_instances }))
+        .type_map_insert::<SourceStatsKey>(source_stats)
+        .type_map_insert::<QueuePagesKey>(Arc::new(RwLock::new(HashMap::new())))
+        .type_map_insert::<RecentCommandsKey>(Arc::new(Mutex::new(HashMap::new())))
+        .type_map_insert::<SearchHistoryKey>(Arc::new(Mutex::new(HashMap::new())))
+        .type_map_insert::<YtdlConfigKey>(Arc::new(ytdl_config))
+        .type_map_insert::<SpotifyCacheKey>(Arc::new(SpotifyCache::load(spotify_cache_path.into())))
+        .type_map_insert::<GuildSettingsKey>(Arc::new(GuildSettingsStore::load(settings_path.into(), prefix, auto_join, idle_timeout)))
         .framework(framework)
         .await
         .expect("Error creating serenity client");
@@ -65,6 +237,16 @@ async fn main() {
     }
 }
 
+// Applied to every YouTube search
+struct YouTubeFilters {
+    // ISO 3166-1 region, ex. "US"
+    region: Option<String>,
+    // ISO 639-1 language, ex. "en"
+    language: Option<String>,
+    // none, moderate or strict
+    safe_search: Option<String>,
+}
+
 // Key to get api access from context type map
 struct ApiAccessKey;
 impl TypeMapKey for ApiAccessKey {
@@ -73,118 +255,300 @@ impl TypeMapKey for ApiAccessKey {
 
 struct ApiAccess {
     youtube_key: String,
+    youtube_filters: YouTubeFilters,
     http: Arc<Client>,
     spotify_token: Arc<RwLock<String>>,
+    // the token stops working after this, the health check fails once it passes
+    spotify_token_expiry: RwLock<Instant>,
+    // used to fetch a new token before the current one expires
+    spotify_id: String,
+    spotify_secret: String,
+    // most songs one playlist or album can add
+    max_playlist_size: usize,
+    // added to the search for Spotify tracks, ex. "official audio"
+    spotify_search_terms: String,
 }
 
 impl ApiAccess {
-    async fn new(youtube_key: String, spotify_id: String, spotify_secret: String) -> ApiAccess {
-        let http = Arc::new(Client::new());
+    // The Spotify token is empty until refresh_spotify_token is called
+    fn new(youtube_key: String, youtube_filters: YouTubeFilters, spotify_id: String, spotify_secret: String, max_playlist_size: usize, spotify_search_terms: String) -> ApiAccess {
+        ApiAccess {
+            youtube_key,
+            youtube_filters,
+            http: Arc::new(Client::new()),
+            spotify_token: Arc::new(RwLock::new(String::new())),
+            spotify_token_expiry: RwLock::new(Instant::now()),
+            spotify_id,
+            spotify_secret,
+            max_playlist_size,
+            spotify_search_terms,
+        }
+    }
+
+    // Replaces the Spotify token with a new one, returning how long it lasts
+    async fn refresh_spotify_token(&self) -> reqwest::Result<Duration> {
+        let credentials = generate_spotify_token(&self.http, &self.spotify_id, &self.spotify_secret).await?;
+        *self.spotify_token.write().await = credentials.access_token;
+        let lifetime = Duration::from_secs(credentials.expires_in);
+        *self.spotify_token_expiry.write().await = Instant::now() + lifetime;
+        Ok(lifetime)
+    }
 
-        let token_lock = Arc::new(RwLock::new(String::new()));
+    // False when refreshing has kept failing until the token expired
+    async fn spotify_token_valid(&self) -> bool {
+        Instant::now() < *self.spotify_token_expiry.read().await
+    }
 
-        {
-            let mut token = (&token_lock).write().await;
-            *token = generate_spotify_token(&http, &spotify_id, &spotify_secret).await;
+    // Fetches a new Spotify token shortly before each one expires, tokens only last an hour
+    async fn keep_spotify_token_fresh(self: Arc<Self>, lifetime: Duration) {
+        let mut wait = lifetime.saturating_sub(SPOTIFY_TOKEN_MARGIN);
+        loop {
+            tokio::time::sleep(wait).await;
+            wait = match self.refresh_spotify_token().await {
+                Ok(lifetime) => lifetime.saturating_sub(SPOTIFY_TOKEN_MARGIN),
+                Err(why) => {
+                    println!("Error refreshing Spotify token: {:?}", why);
+                    SPOTIFY_TOKEN_RETRY
+                }
+            };
         }
+    }
 
-        ApiAccess {
-            youtube_key,
-            http,
-            spotify_token: token_lock,
+    // GETs a YouTube Data API endpoint. Failed requests are logged and give an empty response,
+    // except for running out of quota which callers tell the user about
+    async fn get_youtube<T: DeserializeOwned + Default>(&self, url: String) -> Result<T, QuotaExceeded> {
+        let response = match self.http.get(url).send().await {
+            Ok(response) => response,
+            Err(why) => {
+                println!("Failed to access YouTube API: {:?}", why);
+                return Ok(T::default());
+            }
+        };
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            // ex. {"error": {"code": 403, "errors": [{"reason": "quotaExceeded", ...}]}}
+            if body.contains("\"quotaExceeded\"") {
+                return Err(QuotaExceeded);
+            }
+            println!("YouTube API returned {}: {}", status, body);
+            return Ok(T::default());
+        }
+
+        match response.json::<T>().await {
+            Ok(value) => Ok(value),
+            Err(why) => {
+                println!("Error parsing YouTube API response: {:?}", why);
+                Ok(T::default())
+            }
         }
     }
 
-    async fn search_yt(&self, query: impl std::fmt::Display) -> SearchResult {
+    async fn search_yt(&self, query: impl std::fmt::Display) -> Result<SearchResult, QuotaExceeded> {
         // we do not need &part=snippet
         // todo look into using a form instead of format! for the args
-        let req = format!("https://www.googleapis.com/youtube/v3/search?part=snippet&maxResults=5&type=video&q={}&key={}", query, self.youtube_key);
-        let res = self.http.get(req)
-            .send()
-            .await
-            .expect("Failed to access YouTube API");
-        res.json::<SearchResult>()
-            .await
-            .expect(&format!("Error parsing search response"))
+        let mut req = format!("https://www.googleapis.com/youtube/v3/search?part=snippet&maxResults=5&type=video&q={}&key={}", query, self.youtube_key);
+        if let Some(region) = &self.youtube_filters.region {
+            req.push_str(&format!("&regionCode={}", region));
+        }
+        if let Some(language) = &self.youtube_filters.language {
+            req.push_str(&format!("&relevanceLanguage={}", language));
+        }
+        if let Some(safe_search) = &self.youtube_filters.safe_search {
+            req.push_str(&format!("&safeSearch={}", safe_search));
+        }
+        self.get_youtube(req).await
     }
 
-    async fn get_video_info(&self, video_id: &str) -> YouTubeVideo {
+    // Looks up every video in a playlist. Private and deleted videos are left out.
+    // Returns up to max_playlist_size videos and the total length of the playlist
+    async fn get_playlist_videos(&self, playlist_id: &str) -> Result<(Vec<YouTubeVideo>, usize), QuotaExceeded> {
+        #[derive(Deserialize, Default)]
+        struct PlaylistItemListResponse {
+            items: Vec<PlaylistItem>,
+            #[serde(rename="nextPageToken")]
+            next_page_token: Option<String>,
+            #[serde(rename="pageInfo")]
+            page_info: PageInfo,
+        }
+        #[derive(Deserialize, Default)]
+        struct PageInfo {
+            #[serde(rename="totalResults")]
+            total_results: usize,
+        }
         #[derive(Deserialize)]
-        struct ListResponse {
-            items: [VideoObject; 1],
+        struct PlaylistItem {
+            #[serde(rename="contentDetails")]
+            content_details: PlaylistItemDetails,
         }
         #[derive(Deserialize)]
-        struct VideoObject {
-            snippet: SnippetPart,
+        struct PlaylistItemDetails {
+            #[serde(rename="videoId")]
+            video_id: String,
         }
-        let res = self.http.get(format!("https://www.googleapis.com/youtube/v3/videos?part=snippet&id={}&key={}", video_id, self.youtube_key))
-            .send()
-            .await
-            .expect("Failed to access YouTube API");
-        let list = res.json::<ListResponse>()
-            .await
-            .expect("Error parsing response");
-        let video = &list.items[0];
 
-        YouTubeVideo {
-            name: video.snippet.title.clone(),
-            channel: video.snippet.channel_title.clone(),
-            duration: self.get_video_duration(video_id).await,
-            id: video_id.to_string(),
+        let mut video_ids = Vec::new();
+        let mut page_token = String::new();
+        let total = loop {
+            let url = format!("https://www.googleapis.com/youtube/v3/playlistItems?part=contentDetails&maxResults=50&playlistId={}&pageToken={}&key={}", playlist_id, page_token, self.youtube_key);
+            let page = self.get_youtube::<PlaylistItemListResponse>(url).await?;
+
+            video_ids.extend(page.items.into_iter().map(|item| item.content_details.video_id));
+
+            if video_ids.len() >= self.max_playlist_size {
+                video_ids.truncate(self.max_playlist_size);
+                break page.page_info.total_results;
+            }
+            match page.next_page_token {
+                Some(token) => page_token = token,
+                None => break page.page_info.total_results,
+            }
+        };
+
+        let mut videos = Vec::new();
+        // the videos endpoint accepts at most 50 ids at once
+        for ids in video_ids.chunks(50) {
+            videos.extend(self.get_videos(ids).await?);
         }
+        Ok((videos, total.max(video_ids.len())))
     }
 
-    async fn get_video_duration(&self, video_id: impl AsRef<str> + std::fmt::Display) -> Duration {
+    // Title of a playlist, None if it couldn't be looked up
+    async fn get_playlist_name(&self, playlist_id: &str) -> Result<Option<String>, QuotaExceeded> {
+        #[derive(Deserialize, Default)]
+        struct PlaylistListResponse {
+            items: Vec<PlaylistResource>,
+        }
+        #[derive(Deserialize)]
+        struct PlaylistResource {
+            snippet: PlaylistSnippet,
+        }
         #[derive(Deserialize)]
+        struct PlaylistSnippet {
+            title: String,
+        }
+
+        let url = format!("https://www.googleapis.com/youtube/v3/playlists?part=snippet&id={}&key={}", playlist_id, self.youtube_key);
+        let response = self.get_youtube::<PlaylistListResponse>(url).await?;
+
+        Ok(response.items.into_iter().next().map(|playlist| playlist.snippet.title))
+    }
+
+    async fn get_videos(&self, video_ids: &[String]) -> Result<Vec<YouTubeVideo>, QuotaExceeded> {
+        #[derive(Deserialize, Default)]
         struct VideoListResponse {
-            items: [VideoDetailsResponse; 1],
+            items: Vec<VideoResource>,
         }
         #[derive(Deserialize)]
-        struct VideoDetailsResponse {
+        struct VideoResource {
+            id: String,
+            snippet: SnippetPart,
             #[serde(rename="contentDetails")]
             content_details: ContentDetails,
+            #[serde(rename="liveStreamingDetails")]
+            live_streaming_details: Option<LiveStreamingDetails>,
         }
         #[derive(Deserialize)]
         struct ContentDetails {
-            // ISO 8601 duration string
             duration: String,
         }
-        let url = format!("https://www.googleapis.com/youtube/v3/videos?part=contentDetails&id={}&key={}", video_id, self.youtube_key);
-        let video_list = self.http.get(url)
-            .send()
-            .await
-            .expect("Failed to access YouTube API")
-            .json::<VideoListResponse>()
-            .await
-            .expect("Error parsing response");
+        #[derive(Deserialize)]
+        struct LiveStreamingDetails {
+            // a number in a string, missing once the stream is over
+            #[serde(rename="concurrentViewers")]
+            concurrent_viewers: Option<String>,
+        }
+        let url = format!("https://www.googleapis.com/youtube/v3/videos?part=snippet,contentDetails,liveStreamingDetails&id={}&key={}", video_ids.join(","), self.youtube_key);
+        let video_list = self.get_youtube::<VideoListResponse>(url).await?;
 
-        let duration = &(video_list.items[0]).content_details.duration;
+        Ok(video_list.items
+            .into_iter()
+            .map(|video| YouTubeVideo {
+                name: video.snippet.title,
+                channel: video.snippet.channel_title,
+                duration: duration_from_iso_8601(&video.content_details.duration),
+                id: video.id,
+                chapters: parse_chapters(&video.snippet.description),
+                live: video.snippet.live_broadcast_content == "live",
+                viewers: video.live_streaming_details
+                    .and_then(|details| details.concurrent_viewers)
+                    .and_then(|viewers| viewers.parse().ok()),
+            })
+            .collect())
+    }
+
+    // None when the track was removed, is region restricted, or came back without a name or artists
+    async fn get_spotify_track(&self, track_id: &str) -> Option<SpotifyTrack> {
+        let response = self.http.get(format!("https://api.spotify.com/v1/tracks/{}", track_id))
+            .bearer_auth(self.spotify_token.read().await)
+            .header("Content-Type", "application/json")
+            .send()
+            .await;
 
-        duration_from_iso_8601(duration)
+        match response {
+            Ok(response) if response.status().is_success() => match response.json::<SpotifyTrack>().await {
+                Ok(track) if !track.name.is_empty() && !track.artists.is_empty() => Some(track),
+                Ok(_) => {
+                    println!("Spotify track {} has no name or artists", track_id);
+                    None
+                }
+                Err(why) => {
+                    println!("Error parsing Spotify track {}: {:?}", track_id, why);
+                    None
+                }
+            },
+            Ok(response) => {
+                println!("Spotify returned {} for track {}", response.status(), track_id);
+                None
+            }
+            Err(why) => {
+                println!("Failed to access Spotify API: {:?}", why);
+                None
+            }
+        }
     }
 
-    async fn get_spotify_track(&self, track_id: &str) -> SpotifyTrack {
-        let res = self.http.get(format!("https://api.spotify.com/v1/tracks/{}", track_id))
+    // None when Spotify can't be reached or refuses, ex. an expired token
+    async fn get_spotify_recommendations(&self, seed_track_id: &str) -> Option<Vec<SpotifyTrack>> {
+        #[derive(Deserialize)]
+        struct RecommendationsResponse {
+            tracks: Vec<SpotifyTrack>,
+        }
+        let response = self.http.get(format!("https://api.spotify.com/v1/recommendations?seed_tracks={}&limit=5", seed_track_id))
             .bearer_auth(self.spotify_token.read().await)
             .header("Content-Type", "application/json")
             .send()
-            .await
-            .expect("Failed to access Spotify API");
-        res.json::<SpotifyTrack>()
-            .await
-            .expect("Error parsing response")
+            .await;
+
+        match response {
+            Ok(response) if response.status().is_success() => match response.json::<RecommendationsResponse>().await {
+                Ok(recommendations) => Some(recommendations.tracks),
+                Err(why) => {
+                    println!("Error parsing Spotify recommendations for {}: {:?}", seed_track_id, why);
+                    None
+                }
+            },
+            Ok(response) => {
+                println!("Spotify returned {} for recommendations for {}", response.status(), seed_track_id);
+                None
+            }
+            Err(why) => {
+                println!("Failed to access Spotify API: {:?}", why);
+                None
+            }
+        }
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 struct SearchResult {
-    items: [VideoObject; 5],
+    items: Vec<VideoObject>,
 }
 
 #[derive(Deserialize)]
 struct VideoObject {
     id: VideoId,
-    snippet: SnippetPart,
 }
 
 #[derive(Deserialize)]
@@ -198,6 +562,11 @@ struct SnippetPart {
     title: String,
     #[serde(rename="channelTitle")]
     channel_title: String,
+    #[serde(default)]
+    description: String,
+    // "live" while the video is being streamed
+    #[serde(rename="liveBroadcastContent", default)]
+    live_broadcast_content: String,
 }
 
 struct YouTubeVideo {
@@ -205,6 +574,10 @@ struct YouTubeVideo {
     channel: String,
     duration: Duration,
     id: String,
+    chapters: Vec<Chapter>,
+    live: bool,
+    // people watching a livestream right now
+    viewers: Option<u64>,
 }
 
 impl YouTubeVideo {
@@ -214,22 +587,71 @@ impl YouTubeVideo {
 
     fn as_song(&self, author: String) -> Song {
         Song {
+            thumbnail_url: Some(format!("https://img.youtube.com/vi/{}/mqdefault.jpg", self.id)),
+            chapters: self.chapters.clone(),
+            live: self.live,
             // decode HTML characters
-            title: self.name.clone().replace("&#39;", "'"),
-            artist: self.channel.clone(),
-            author,
-            duration: self.duration,
-            source: SongSource::YouTube { 
-                id: self.id.clone(),
-                url: self.url(),
-            },
-            handle: None,
+            ..Song::new(self.name.clone().replace("&#39;", "'"), artist_name(&self.channel), author, self.duration, SongSource::YouTube { url: self.url() })
         }
     }
 }
 
-struct VideoDetails {
-    duration: Duration,
+// Tidies the auto-generated channel names music is often uploaded to,
+// ex. "Artist - Topic" becomes "Artist" and "ArtistNameVEVO" becomes "Artist Name"
+fn artist_name(channel: &str) -> String {
+    if let Some(artist) = channel.strip_suffix(" - Topic") {
+        return artist.to_owned();
+    }
+    match channel.strip_suffix("VEVO").map(str::trim_end) {
+        Some(artist) if !artist.is_empty() => {
+            // VEVO channel names leave out the spaces
+            let mut name = String::new();
+            let mut previous = ' ';
+            for c in artist.chars() {
+                if c.is_uppercase() && previous.is_lowercase() {
+                    name.push(' ');
+                }
+                name.push(c);
+                previous = c;
+            }
+            name
+        }
+        _ => channel.to_owned(),
+    }
+}
+
+// Reads chapters from timestamped lines in a video description, ex. "1:02:03 - Song name".
+// Like YouTube, only counts them when the first starts at 0:00 and there are at least three.
+fn parse_chapters(description: &str) -> Vec<Chapter> {
+    let chapters = description
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let (timestamp, title) = line.split_once(char::is_whitespace)?;
+            let start = parse_timestamp(timestamp.trim_matches(|c| c == '(' || c == ')' || c == '[' || c == ']'))?;
+            let title = title.trim_start_matches(|c: char| c.is_whitespace() || matches!(c, '-' | '–' | '|' | ':')).trim();
+
+            (!title.is_empty()).then(|| Chapter { start, title: title.to_owned() })
+        })
+        .collect::<Vec<_>>();
+
+    let ascending = chapters.windows(2).all(|pair| pair[0].start < pair[1].start);
+    if chapters.len() >= 3 && chapters[0].start == Duration::ZERO && ascending {
+        chapters
+    } else {
+        Vec::new()
+    }
+}
+
+// Parses "m:ss" or "h:mm:ss"
+fn parse_timestamp(timestamp: &str) -> Option<Duration> {
+    let parts = timestamp.split(':').map(|part| part.parse::<u64>().ok()).collect::<Option<Vec<_>>>()?;
+
+    match parts[..] {
+        [minutes, seconds] if seconds < 60 => Some(Duration::from_secs(minutes * 60 + seconds)),
+        [hours, minutes, seconds] if minutes < 60 && seconds < 60 => Some(Duration::from_secs(hours * 3600 + minutes * 60 + seconds)),
+        _ => None,
+    }
 }
 
 fn duration_from_iso_8601(duration_string: &str) -> Duration {
@@ -238,9 +660,23 @@ fn duration_from_iso_8601(duration_string: &str) -> Duration {
 
 #[derive(Deserialize)]
 struct SpotifyTrack {
+    id: String,
+    album: SpotifyAlbum,
     artists: Vec<SpotifyArtist>,
-    duration_ms: u64,
     name: String,
+    #[serde(default)]
+    explicit: bool,
+}
+
+#[derive(Deserialize)]
+struct SpotifyAlbum {
+    // largest first
+    images: Vec<SpotifyImage>,
+}
+
+#[derive(Deserialize)]
+struct SpotifyImage {
+    url: String,
 }
 
 #[derive(Deserialize)]
@@ -248,27 +684,164 @@ struct SpotifyArtist {
     name: String,
 }
 
+// How long before a Spotify token expires a new one is fetched, and how long to wait to try again if that fails
+const SPOTIFY_TOKEN_MARGIN: Duration = Duration::from_secs(300);
+const SPOTIFY_TOKEN_RETRY: Duration = Duration::from_secs(30);
+
 // This method uses the client credentials flow.
-async fn generate_spotify_token(client: &Client, client_id: &String, client_secret: &String) -> String {
+async fn generate_spotify_token(client: &Client, client_id: &String, client_secret: &String) -> reqwest::Result<ClientCredentialsResponse> {
     let params = [("grant_type", "client_credentials")];
 
-    let res = client.post("https://accounts.spotify.com/api/token")
+    client.post("https://accounts.spotify.com/api/token")
         .header("Authorization", format!("Basic {}", base64::encode(format!("{}:{}", client_id, client_secret))))
         .form(&params)
         .send()
+        .await?
+        .error_for_status()?
+        .json::<ClientCredentialsResponse>()
         .await
-        .expect("Failed to generate new Spotify token");
-
-    let credentials = res.json::<ClientCredentialsResponse>()
-        .await
-        .expect("Error parsing response");
-
-    credentials.access_token
 }
 
 #[derive(Deserialize)]
 struct ClientCredentialsResponse {
     access_token: String,
+    // seconds
+    expires_in: u64,
+}
+
+// Subset of the JSON printed by `youtube-dl --dump-json`
+#[derive(Deserialize)]
+struct YtdlInfo {
+    title: String,
+    uploader: Option<String>,
+    // seconds, missing for livestreams
+    duration: Option<f64>,
+    webpage_url: String,
+    thumbnail: Option<String>,
+    // set for tracks from a playlist or album
+    playlist_title: Option<String>,
+    chapters: Option<Vec<YtdlChapter>>,
+    is_live: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct YtdlChapter {
+    // seconds
+    start_time: f64,
+    title: String,
+}
+
+impl YtdlInfo {
+    fn as_song(&self, author: String) -> Song {
+        let source = match SourceKind::from_link(&self.webpage_url) {
+            Some(SourceKind::SoundCloud) => SongSource::SoundCloud { url: self.webpage_url.clone() },
+            Some(SourceKind::Bandcamp) => SongSource::Bandcamp { url: self.webpage_url.clone() },
+            _ => SongSource::Extractor { url: self.webpage_url.clone() },
+        };
+        Song {
+            thumbnail_url: self.thumbnail.clone(),
+            chapters: self.chapters
+                .iter()
+                .flatten()
+                .map(|chapter| Chapter { start: Duration::from_secs_f64(chapter.start_time), title: chapter.title.clone() })
+                .collect(),
+            live: self.is_live.unwrap_or(false),
+            ..Song::new(self.title.clone(), self.uploader.clone().unwrap_or_default(), author, Duration::from_secs_f64(self.duration.unwrap_or(0.0)), source)
+        }
+    }
+}
+
+// Resolves a URL or a youtube-dl search string (ex. "scsearch1:query") without downloading anything
+async fn ytdl_info(config: &YtdlConfig, query: &str) -> Option<YtdlInfo> {
+    let output = tokio::process::Command::new(&config.command)
+        .args(["--dump-json", "--no-playlist", "--ignore-config", "--no-warnings"])
+        .args(&config.extra_args)
+        .arg(query)
+        .output()
+        .await
+        .ok()?;
+
+    if !output.status.success() {
+        println!("{} failed to resolve {}: {}", config.command, query, String::from_utf8_lossy(&output.stderr));
+        return None;
+    }
+
+    serde_json::from_slice(&output.stdout).ok()
+}
+
+// Downloads the audio of a link into the download directory and returns the file's path.
+// Printing the final path needs yt-dlp, youtube-dl doesn't support --print.
+async fn ytdl_download(config: &YtdlConfig, url: &str) -> Option<PathBuf> {
+    if let Err(why) = tokio::fs::create_dir_all(&config.download_dir).await {
+        println!("Error creating download directory: {:?}", why);
+        return None;
+    }
+
+    let template = config.download_dir.join("%(title)s [%(id)s].%(ext)s");
+    let output = tokio::process::Command::new(&config.command)
+        .args(["-x", "-f", &config.format, "--no-playlist", "--ignore-config", "--no-warnings", "--print", "after_move:filepath", "-o"])
+        .arg(template)
+        .args(&config.extra_args)
+        .arg(url)
+        .output()
+        .await
+        .ok()?;
+
+    if !output.status.success() {
+        println!("{} failed to download {}: {}", config.command, url, String::from_utf8_lossy(&output.stderr));
+        return None;
+    }
+
+    String::from_utf8_lossy(&output.stdout).lines().last().map(PathBuf::from)
+}
+
+// Resolves every entry of a playlist or album, youtube-dl prints one JSON object per line
+async fn ytdl_playlist_info(config: &YtdlConfig, url: &str, max: usize) -> Vec<YtdlInfo> {
+    let output = match tokio::process::Command::new(&config.command)
+        .args(["--dump-json", "--yes-playlist", "--ignore-config", "--no-warnings", "--playlist-end", &max.to_string()])
+        .args(&config.extra_args)
+        .arg(url)
+        .output()
+        .await {
+        Ok(output) => output,
+        Err(why) => {
+            println!("Error running {}: {:?}", config.command, why);
+            return Vec::new();
+        }
+    };
+
+    output.stdout
+        .split(|&byte| byte == b'\n')
+        .filter_map(|line| serde_json::from_slice(line).ok())
+        .collect()
+}
+
+// How youtube-dl is run, so self-hosters can trade quality for bandwidth or work around extraction issues
+struct YtdlConfig {
+    // ex. "yt-dlp"
+    command: String,
+    // youtube-dl format selection, ex. "bestaudio[acodec=opus]/bestaudio"
+    format: String,
+    // passed to every youtube-dl call
+    extra_args: Vec<String>,
+    // where the savesong command puts audio files, played back with "file:<name>"
+    download_dir: PathBuf,
+}
+
+struct YtdlConfigKey;
+impl TypeMapKey for YtdlConfigKey {
+    type Value = Arc<YtdlConfig>;
+}
+
+// Reads the length of an audio file or stream with ffprobe
+async fn ffprobe_duration(url: &str) -> Option<Duration> {
+    let output = tokio::process::Command::new("ffprobe")
+        .args(["-v", "error", "-show_entries", "format=duration", "-of", "default=noprint_wrappers=1:nokey=1", url])
+        .output()
+        .await
+        .ok()?;
+
+    String::from_utf8_lossy(&output.stdout).trim().parse::<f64>().ok().map(Duration::from_secs_f64)
 }
 
 struct PerServerQueueAccessKey;
@@ -278,6 +851,12 @@ impl TypeMapKey for PerServerQueueAccessKey {
 
 struct PerServerQueue {
     map: RwLock<HashMap<GuildId, Arc<Mutex<ServerQueue>>>>,
+    // QUEUE_SUMMARY_SIZE
+    summary_size: usize,
+    // MAX_VOICE_CONNECTIONS, unlimited when unset
+    max_voice_connections: Option<usize>,
+    // OTHER_INSTANCE_IDS
+    other_instances: HashSet<UserId>,
 }
 
 impl PerServerQueue {
@@ -289,7 +868,7 @@ impl PerServerQueue {
             // drop the read lock
             drop(map);
             let mut map = self.map.write().await;
-            map.insert(*guild_id, Arc::new(Mutex::new(ServerQueue { now_playing: None, queue: VecDeque::new() })));
+            map.insert(*guild_id, Arc::new(Mutex::new(ServerQueue { guild_id: *guild_id, ..Default::default() })));
             map.get(guild_id).unwrap().clone()
         } else {
             map.get(guild_id).unwrap().clone()
@@ -303,9 +882,36 @@ impl PerServerQueue {
 }
 
 // These are only accessed from a Mutex so no thread handling should be necessary
+#[derive(Default)]
 struct ServerQueue {
+    guild_id: GuildId,
     now_playing: Option<Song>,
+    // songs are added and removed with the methods below, which keep total_duration up to date
     queue: VecDeque<Song>,
+    // of every song in queue, kept as songs come and go so it's never summed
+    total_duration: Duration,
+    // loop mode of the now playing song, mirrors its TrackHandle
+    looping: Option<LoopState>,
+    // whether to pick similar songs when the queue runs out
+    autoplay: bool,
+    // applied to every song as it starts
+    eq: EqPreset,
+    // set when playback was paused because everyone left the voice channel
+    paused_alone: bool,
+    // when summon last moved the bot between voice channels
+    last_moved: Option<Instant>,
+    // when the queue ran out or playback was paused, the bot leaves after the server's idle timeout
+    idle_since: Option<Instant>,
+    // where music was last requested, songs resumed after a restart are announced there
+    text_channel: Option<ChannelId>,
+    // song ids in queue order from before the last rearrangement, used by undo
+    undo_order: Option<Vec<u64>>,
+    // songs the last change took out of the queue, undo puts them back
+    undo_removed: Vec<Song>,
+    // the now playing embed kept at the bottom of the sticky channel, and when it was posted
+    sticky_message: Option<(MessageId, Instant)>,
+    // whether now playing embeds are edited as the song plays, off to save on rate limits
+    live_progress: bool,
 }
 
 impl ServerQueue {
@@ -315,41 +921,460 @@ impl ServerQueue {
 
     fn stop(&mut self) {
         self.now_playing = None;
+        self.clear_queue();
+    }
+
+    fn push_back(&mut self, song: Song) {
+        self.total_duration += song.duration;
+        self.queue.push_back(song);
+    }
+
+    fn push_front(&mut self, song: Song) {
+        self.total_duration += song.duration;
+        self.queue.push_front(song);
+    }
+
+    fn insert(&mut self, index: usize, song: Song) {
+        self.total_duration += song.duration;
+        self.queue.insert(index, song);
+    }
+
+    fn pop_front(&mut self) -> Option<Song> {
+        let song = self.queue.pop_front()?;
+        self.total_duration = self.total_duration.saturating_sub(song.duration);
+        Some(song)
+    }
+
+    fn remove(&mut self, index: usize) -> Option<Song> {
+        let song = self.queue.remove(index)?;
+        self.total_duration = self.total_duration.saturating_sub(song.duration);
+        Some(song)
+    }
+
+    // Swaps the song at index for another, returning the old one
+    fn replace(&mut self, index: usize, song: Song) -> Song {
+        self.total_duration = self.total_duration.saturating_sub(self.queue[index].duration) + song.duration;
+        std::mem::replace(&mut self.queue[index], song)
+    }
+
+    // Moves the song at index on to its next search result. Returns false if it has none left
+    fn use_next_alternate(&mut self, index: usize) -> bool {
+        let old_duration = self.queue[index].duration;
+        if !self.queue[index].use_next_alternate() {
+            return false;
+        }
+        self.total_duration = self.total_duration.saturating_sub(old_duration) + self.queue[index].duration;
+        true
+    }
+
+    // Takes out the first count songs
+    fn drain_front(&mut self, count: usize) -> Vec<Song> {
+        let songs = self.queue.drain(..count).collect::<Vec<_>>();
+        for song in &songs {
+            self.total_duration = self.total_duration.saturating_sub(song.duration);
+        }
+        songs
+    }
+
+    fn clear_queue(&mut self) {
         self.queue.clear();
+        self.total_duration = Duration::ZERO;
+    }
+
+    // Removes queued songs that repeat the now playing song or an earlier queued one,
+    // keeping the first of each. Returns how many were removed.
+    fn remove_duplicates(&mut self) -> usize {
+        let len = self.queue.len();
+        let keep = (0..len)
+            .map(|i| {
+                let source = &self.queue[i].source;
+                self.now_playing.as_ref().is_none_or(|song| song.source != *source)
+                    && !self.queue.iter().take(i).any(|earlier| earlier.source == *source)
+            })
+            .collect::<Vec<_>>();
+
+        let mut keep = keep.into_iter();
+        let mut removed = Duration::ZERO;
+        self.queue.retain(|song| {
+            let kept = keep.next().unwrap();
+            if !kept {
+                removed += song.duration;
+            }
+            kept
+        });
+        self.total_duration = self.total_duration.saturating_sub(removed);
+        len - self.queue.len()
+    }
+
+    // Remembers the current order so the next change can be undone
+    fn save_undo(&mut self) {
+        self.undo_order = Some(self.queue.iter().map(|song| song.id).collect());
+        self.undo_removed.clear();
+    }
+
+    // Puts the queue back in the saved order, including songs the change removed. Songs queued
+    // since then stay at the end, songs that already played are gone. Returns false if there was nothing to undo.
+    fn undo(&mut self) -> bool {
+        match self.undo_order.take() {
+            Some(order) => {
+                for song in std::mem::take(&mut self.undo_removed) {
+                    self.push_back(song);
+                }
+                let positions: HashMap<u64, usize> = order.into_iter().enumerate().map(|(i, id)| (id, i)).collect();
+                self.queue.make_contiguous().sort_by_key(|song| positions.get(&song.id).copied().unwrap_or(usize::MAX));
+                true
+            }
+            None => false,
+        }
     }
 
     // Shifts the songs forward after the front song ends
     fn shift_queue(&mut self) {
-        self.now_playing = self.queue.pop_front();
+        self.now_playing = self.pop_front();
+        self.looping = None;
+        self.paused_alone = false;
     }
 }
 
 struct Song {
+    // unique per queued song, lets the queue order be saved without copying songs
+    id: u64,
     title: String,
     artist: String,
     author: String,
     duration: Duration,
     source: SongSource,
+    // where playback begins, ex. from a timestamped link
+    start: Option<Duration>,
+    // where playback stops when only a clip of the song was asked for
+    end: Option<Duration>,
+    // set when the song was resolved from Spotify, used to seed autoplay
+    spotify_id: Option<String>,
+    // artwork shown in embeds
+    thumbnail_url: Option<String>,
+    // sections of long videos like DJ mixes, in order
+    chapters: Vec<Chapter>,
+    // livestreams have no duration, so now playing shows them as live instead
+    live: bool,
+    // the next search results, played instead if this one fails to start
+    alternates: Vec<Song>,
+    // who queued the song, pinged when it starts in servers with pingrequester on.
+    // Taken once they've been pinged so restarts (ex. reconnect) don't ping again
+    requester: Option<UserId>,
+    // set once the song was started again after ending right away, so a broken source isn't retried forever
+    retried: bool,
     handle: Option<TrackHandle>,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+struct Chapter {
+    start: Duration,
+    title: String,
+}
+
+static NEXT_SONG_ID: AtomicU64 = AtomicU64::new(0);
+
+// Keeps a page of ten queue entries well under Discord's 4096 character embed description limit
+const MAX_TITLE_LENGTH: usize = 150;
+
+// How many other search results a song keeps in case it can't be played
+const MAX_ALTERNATES: usize = 2;
+
+// Shortens text to at most max characters, ending with an ellipsis when it was cut
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_owned()
+    } else {
+        let mut truncated = text.chars().take(max - 1).collect::<String>();
+        truncated.push('…');
+        truncated
+    }
+}
+
 impl Song {
+    // A song with only what every source has. Anything else (ex. artwork) is filled in with
+    // struct update syntax, so fields that start out empty don't need adding to every source
+    fn new(title: String, artist: String, author: String, duration: Duration, source: SongSource) -> Song {
+        Song {
+            id: Song::next_id(),
+            title,
+            artist,
+            author,
+            duration,
+            source,
+            start: None,
+            end: None,
+            spotify_id: None,
+            thumbnail_url: None,
+            chapters: Vec::new(),
+            live: false,
+            alternates: Vec::new(),
+            requester: None,
+            retried: false,
+            handle: None,
+        }
+    }
+
+    fn next_id() -> u64 {
+        NEXT_SONG_ID.fetch_add(1, Ordering::Relaxed)
+    }
+
+    // The chapter playing at a position in the song
+    fn chapter_at(&self, position: Duration) -> Option<&Chapter> {
+        self.chapters.iter().rev().find(|chapter| chapter.start <= position)
+    }
+
+    // The title cut down to fit in embeds and messages, some sites allow very long titles
+    fn short_title(&self) -> String {
+        truncate(&self.title, MAX_TITLE_LENGTH)
+    }
+
+    // Swaps in the next search result, keeping the ones after it. Returns false if there are none left.
+    // Only what describes the source changes, who asked for the song and which part of it stay the same
+    fn use_next_alternate(&mut self) -> bool {
+        if self.alternates.is_empty() {
+            return false;
+        }
+        let next = self.alternates.remove(0);
+        self.id = next.id;
+        self.title = next.title;
+        self.artist = next.artist;
+        self.duration = next.duration;
+        self.source = next.source;
+        self.chapters = next.chapters;
+        self.live = next.live;
+        self.retried = false;
+        self.handle = None;
+        true
+    }
+
     fn title_with_link(&self) -> String {
-        match &self.source {
-            SongSource::YouTube { id: _, url } => format!("[{}]({})", self.title, url),
-            _ => format!("{} (Local files)", self.title),
+        match self.source {
+            // a path isn't a link anyone else can open
+            SongSource::Local { path: _ } => self.short_title(),
+            _ => format!("[{}]({})", self.short_title(), self.source.url()),
         }
     }
 }
 
+// Where songs can come from. get_song dispatches on these, so this is also
+// the list shown by the sources command.
+#[derive(Clone, Copy)]
+enum SourceKind {
+    Spotify,
+    SoundCloud,
+    Bandcamp,
+    YouTube,
+}
+
+impl SourceKind {
+    // In the order links are matched
+    const ALL: [SourceKind; 4] = [SourceKind::Spotify, SourceKind::SoundCloud, SourceKind::Bandcamp, SourceKind::YouTube];
+
+    fn from_link(link: &str) -> Option<SourceKind> {
+        SourceKind::ALL.iter().copied().find(|kind| kind.matches_link(link))
+    }
+
+    fn matches_link(&self, link: &str) -> bool {
+        match self {
+            SourceKind::Spotify => link.contains("spotify.com/track/"),
+            SourceKind::SoundCloud => link.contains("soundcloud.com/"),
+            SourceKind::Bandcamp => link.contains("bandcamp.com/"),
+            SourceKind::YouTube => link.contains("?v=") || link.contains("list="),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            SourceKind::Spotify => "Spotify",
+            SourceKind::SoundCloud => "SoundCloud",
+            SourceKind::Bandcamp => "Bandcamp",
+            SourceKind::YouTube => "YouTube",
+        }
+    }
+
+    fn example_link(&self) -> &'static str {
+        match self {
+            SourceKind::Spotify => "https://open.spotify.com/track/...",
+            SourceKind::SoundCloud => "https://soundcloud.com/artist/track",
+            SourceKind::Bandcamp => "https://artist.bandcamp.com/track/... or https://artist.bandcamp.com/album/...",
+            SourceKind::YouTube => "https://youtube.com/watch?v=... or https://youtube.com/playlist?list=...",
+        }
+    }
+
+    // Prefix that forces a search through this source, ex. "sc:query"
+    fn search_prefix(&self) -> Option<&'static str> {
+        match self {
+            SourceKind::Spotify => None,
+            SourceKind::SoundCloud => Some("sc:"),
+            SourceKind::Bandcamp => None,
+            SourceKind::YouTube => Some("yt:"),
+        }
+    }
+}
+
+// Urls are the page links, never youtube-dl's stream urls. Those expire after a few hours,
+// so they're looked up again every time a song starts, however long it waited in the queue.
+// Direct is the exception, Discord attachment links are signed and stop working after a while.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 enum SongSource {
-    YouTube { id: String, url: String }
+    YouTube { url: String },
+    SoundCloud { url: String },
+    Bandcamp { url: String },
+    // any other site youtube-dl can read
+    Extractor { url: String },
+    // an audio file, ex. a Discord attachment. Not saved for resuming since attachment links expire
+    Direct { url: String },
+    // a file saved in the download directory
+    Local { path: String },
 }
 
 impl SongSource {
-    async fn as_input(&self) -> songbird::input::error::Result<Input> {
+    fn url(&self) -> &str {
+        match self {
+            SongSource::YouTube { url } => url,
+            SongSource::SoundCloud { url } => url,
+            SongSource::Bandcamp { url } => url,
+            SongSource::Extractor { url } => url,
+            SongSource::Direct { url } => url,
+            SongSource::Local { path } => path,
+        }
+    }
+
+    // Names the variant, used to group the source stats
+    fn kind_name(&self) -> &'static str {
+        match self {
+            SongSource::YouTube { .. } => "YouTube",
+            SongSource::SoundCloud { .. } => "SoundCloud",
+            SongSource::Bandcamp { .. } => "Bandcamp",
+            SongSource::Extractor { .. } => "Extractor",
+            SongSource::Direct { .. } => "Direct",
+            SongSource::Local { .. } => "Local",
+        }
+    }
+
+    fn youtube_id(&self) -> Option<&str> {
+        match self {
+            SongSource::YouTube { url } => url.split("?v=").nth(1),
+            _ => None,
+        }
+    }
+
+    // How long the source is now, which can differ from when it was queued (ex. a shorter reupload)
+    async fn current_duration(&self, config: &YtdlConfig) -> Option<Duration> {
         match self {
-            SongSource::YouTube { id: _, url } => songbird::input::ytdl(url).await,
+            SongSource::Direct { url: _ } | SongSource::Local { path: _ } => ffprobe_duration(self.url()).await,
+            _ => Some(Duration::from_secs_f64(ytdl_info(config, self.url()).await?.duration?)),
         }
     }
+
+    // Restartable sources are seekable, which is needed for start offsets
+    async fn as_input(&self, config: Arc<YtdlConfig>, eq: EqPreset) -> songbird::input::error::Result<Input> {
+        let ytdl = match self {
+            // ffmpeg can read audio files by itself
+            SongSource::Direct { url: _ } | SongSource::Local { path: _ } => None,
+            _ => Some(config),
+        };
+
+        Restartable::new(SourceRestarter { url: self.url().to_owned(), ytdl, filter: eq.filter() }, false).await.map(Input::from)
+    }
+}
+
+// Equalizer presets, applied as ffmpeg audio filters
+#[derive(Clone, Copy, Default, PartialEq)]
+enum EqPreset {
+    #[default]
+    Flat,
+    Bass,
+    Treble,
+    Vocal,
+}
+
+impl EqPreset {
+    const ALL: [EqPreset; 4] = [EqPreset::Flat, EqPreset::Bass, EqPreset::Treble, EqPreset::Vocal];
+
+    fn from_name(name: &str) -> Option<EqPreset> {
+        EqPreset::ALL.iter().copied().find(|preset| preset.name().eq_ignore_ascii_case(name))
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            EqPreset::Flat => "flat",
+            EqPreset::Bass => "bass",
+            EqPreset::Treble => "treble",
+            EqPreset::Vocal => "vocal",
+        }
+    }
+
+    // Argument for ffmpeg's -af, flat leaves the audio untouched
+    fn filter(&self) -> Option<&'static str> {
+        match self {
+            EqPreset::Flat => None,
+            EqPreset::Bass => Some("equalizer=f=60:t=o:w=2:g=8,equalizer=f=200:t=o:w=1:g=3"),
+            EqPreset::Treble => Some("equalizer=f=6000:t=o:w=2:g=6,equalizer=f=12000:t=o:w=1:g=4"),
+            EqPreset::Vocal => Some("equalizer=f=100:t=o:w=2:g=-4,equalizer=f=2500:t=o:w=2:g=5"),
+        }
+    }
+}
+
+// Same pipeline as songbird's ytdl source, but using YtdlConfig and an optional ffmpeg audio filter.
+// Without a YtdlConfig, ffmpeg opens the URL itself.
+struct SourceRestarter {
+    url: String,
+    ytdl: Option<Arc<YtdlConfig>>,
+    filter: Option<&'static str>,
+}
+
+#[async_trait]
+impl Restart for SourceRestarter {
+    async fn call_restart(&mut self, time: Option<Duration>) -> songbird::input::error::Result<Input> {
+        let mut children = Vec::new();
+        let mut ffmpeg = std::process::Command::new("ffmpeg");
+        // seeking happens by restarting, so skip ahead in the new stream
+        if let Some(time) = time {
+            ffmpeg.args(["-ss", &format!("{:.3}", time.as_secs_f64())]);
+        }
+
+        match &self.ytdl {
+            Some(config) => {
+                let mut youtube_dl = std::process::Command::new(&config.command)
+                    .args(["-f", &config.format, "-R", "infinite", "--no-playlist", "--ignore-config", "--no-warnings"])
+                    .args(&config.extra_args)
+                    .args([&self.url, "-o", "-"])
+                    .stdin(Stdio::null())
+                    .stderr(Stdio::null())
+                    .stdout(Stdio::piped())
+                    .spawn()?;
+                let youtube_dl_out = youtube_dl.stdout.take().ok_or(songbird::input::error::Error::Stdout)?;
+
+                ffmpeg.args(["-i", "-"]).stdin(youtube_dl_out);
+                children.push(youtube_dl);
+            }
+            None => {
+                ffmpeg.args(["-i", &self.url]).stdin(Stdio::null());
+            }
+        }
+
+        if let Some(filter) = self.filter {
+            ffmpeg.args(["-af", filter]);
+        }
+        let ffmpeg = ffmpeg
+            .args(["-f", "s16le", "-ac", "2", "-ar", "48000", "-acodec", "pcm_f32le", "-"])
+            .stderr(Stdio::null())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        children.push(ffmpeg);
+
+        Ok(Input::new(
+            true,
+            children_to_reader::<f32>(children),
+            Codec::FloatPcm,
+            Container::Raw,
+            None,
+        ))
+    }
+
+    async fn lazy_init(&mut self) -> songbird::input::error::Result<(Option<Metadata>, Codec, Container)> {
+        Ok((None, Codec::FloatPcm, Container::Raw))
+    }
 }
\ No newline at end of file
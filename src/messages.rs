@@ -0,0 +1,526 @@
+use serde::{Deserialize, Serialize};
+use serenity::model::channel::ReactionType;
+
+// Language of the bot's replies, chosen per guild with the language command
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Locale {
+    #[default]
+    English,
+    Spanish,
+}
+
+impl Locale {
+    pub(crate) const ALL: [Locale; 2] = [Locale::English, Locale::Spanish];
+
+    // Accepts the ISO 639-1 code, the English name or the locale's own name, ex. "es", "spanish" or "español"
+    pub(crate) fn from_name(name: &str) -> Option<Locale> {
+        let name = name.to_lowercase();
+        Locale::ALL.iter().copied().find(|locale| {
+            name == locale.code() || name == locale.english_name().to_lowercase() || name == locale.name().to_lowercase()
+        })
+    }
+
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::Spanish => "es",
+        }
+    }
+
+    pub(crate) fn english_name(&self) -> &'static str {
+        match self {
+            Locale::English => "English",
+            Locale::Spanish => "Spanish",
+        }
+    }
+
+    // Shown in the locale itself so people can find their own language
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Locale::English => "English",
+            Locale::Spanish => "Español",
+        }
+    }
+}
+
+// Every reply the bot sends. Commands pick one and it is rendered in the guild's locale.
+pub(crate) enum Reply<'a> {
+    Joined(&'a str),
+    CantConnect,
+    CantSpeak,
+    ChannelFull,
+    JoinTimedOut,
+    VoiceServerFailed,
+    JoinFailed,
+    MustBeInVc,
+    NothingPlaying,
+    ServerOnly,
+    ServerNotCached,
+    NotInVc,
+    SummonFirst,
+    Reconnected,
+    ReconnectFailed,
+    NotSameVc,
+    ConnectedElsewhere(&'a str),
+    OtherInstance,
+    AtCapacity,
+    NoMatches,
+    SpotifyUnavailable,
+    SpotifyCollection,
+    QuotaExceeded,
+    QueriesAdded(usize, usize),
+    QueriesDropped(usize, usize),
+    SourceError,
+    Playing(&'a str),
+    PlayingFor(&'a str, &'a str),
+    StreamLost(&'a str),
+    PlayUsage,
+    ClipUsage,
+    QueueUsage,
+    QueueJumpUsage,
+    QueueFindUsage,
+    NoQueueMatches(&'a str),
+    JumpToUsage,
+    JumpedTo(&'a str),
+    LiveQueueStopped,
+    Paused,
+    Resumed,
+    SkippedTo(&'a str),
+    SkippedEmpty,
+    SkipWait(u64),
+    SkipDelay(u64),
+    SkipDelayUsage,
+    IdleTimeout(u64),
+    IdleTimeoutOff,
+    IdleTimeoutUsage,
+    SearchUsage,
+    SelectionTimedOut,
+    SelectionCancelled,
+    SelectionTimeout(u64),
+    SelectionTimeoutUsage,
+    NoSearchHistory,
+    SearchHistoryUsage,
+    MoveCooldown(u64),
+    MoveCooldownSet(u64),
+    MoveCooldownUsage,
+    PlainTextOn,
+    PlainTextOff,
+    PlainTextUsage,
+    PingRequesterOn,
+    PingRequesterOff,
+    PingRequesterUsage,
+    ReactionsOn,
+    ReactionsOff,
+    ReactionsUsage,
+    QueueEmpty,
+    PositionRange(usize),
+    Moved(&'a str, usize),
+    MoveUsage,
+    RemoveUsage,
+    DuplicatesRemoved(usize),
+    MovedToFront(usize),
+    Removed(&'a str),
+    NotYourSong,
+    Swapped(&'a str),
+    Volume(u32),
+    VolumeRange,
+    VolumeSet(u32),
+    Undone,
+    NothingToUndo,
+    ReplacedWith(&'a str),
+    NothingToReplace,
+    NoOtherResults,
+    Looping,
+    LoopingTimes(usize),
+    LoopStopped,
+    LoopUsage,
+    AutoplayEnabled,
+    AutoplayDisabled,
+    LiveProgressEnabled,
+    LiveProgressDisabled,
+    ClearedCurrent,
+    ClearedUpcoming(usize),
+    ClearedAll(usize),
+    ClearUsage,
+    Equalizer(&'a str, &'a str),
+    UnknownPreset(&'a str),
+    EqualizerSet(&'a str),
+    LeaveGuildUsage,
+    LeftGuild(u64),
+    PausedAll(usize),
+    StoppedAll(usize),
+    Saved(&'a str),
+    AlreadySaved,
+    SaveFailed,
+    CantDisable,
+    Disabled(&'a str),
+    Enabled(&'a str),
+    UnknownCommand(&'a str),
+    Prefix(&'a str),
+    PrefixReset(&'a str),
+    PrefixSet(&'a str),
+    PrefixSpaces,
+    PrefixHint(&'a str),
+    Language(&'a str, &'a str),
+    UnknownLanguage(&'a str),
+    LanguageSet,
+    AutoJoinOn,
+    AutoJoinOff,
+    AutoJoinUsage,
+    SameChannelOn,
+    SameChannelOff,
+    SameChannelUsage,
+    StickyOn(&'a str),
+    StickyOff,
+    StickyUsage,
+    CommandChannelOnly(&'a str),
+    CommandChannelSet(&'a str),
+    CommandChannelCleared,
+    CommandChannelUsage,
+    CommandDisabled,
+    NeedDj,
+    MissingPermission(&'a str),
+    OwnersOnly,
+    Busy,
+    MixUnsupported,
+    PlaylistLimit(usize, usize),
+    AlbumLimit(usize),
+}
+
+impl Reply<'_> {
+    pub(crate) fn render(&self, locale: Locale) -> String {
+        match locale {
+            Locale::English => self.english(),
+            Locale::Spanish => self.spanish(),
+        }
+    }
+
+    // The emoji that stands in for this reply in servers that turned on reactions
+    pub(crate) fn reaction(&self) -> Option<ReactionType> {
+        // Discord only knows the fully qualified forms, with the variation selector after the symbol
+        let emoji = match self {
+            Reply::SkippedTo(_) | Reply::SkippedEmpty => "⏭️",
+            Reply::ClearedAll(_) | Reply::ClearedCurrent => "⏹️",
+            Reply::Paused => "⏸️",
+            Reply::Resumed => "▶️",
+            _ => return None,
+        };
+        Some(ReactionType::Unicode(emoji.to_owned()))
+    }
+
+    fn english(&self) -> String {
+        match self {
+            Reply::Joined(channel) => format!("Joined {}", channel),
+            Reply::CantConnect => "I don't have permission to connect to your voice channel".to_owned(),
+            Reply::CantSpeak => "I don't have permission to speak in your voice channel".to_owned(),
+            Reply::ChannelFull => "Your voice channel is full".to_owned(),
+            Reply::JoinTimedOut => "Discord didn't answer in time, try again in a moment".to_owned(),
+            Reply::VoiceServerFailed => "Couldn't connect to the voice server for this channel's region, try again or change the region".to_owned(),
+            Reply::JoinFailed => "Couldn't join your voice channel".to_owned(),
+            Reply::MustBeInVc => "Must be in a voice channel to use this command".to_owned(),
+            Reply::NothingPlaying => "Nothing playing".to_owned(),
+            Reply::ServerOnly => "This command can only be used in a server".to_owned(),
+            Reply::ServerNotCached => "Couldn't resolve this server, try again in a moment".to_owned(),
+            Reply::NotInVc => "Not in a voice channel".to_owned(),
+            Reply::SummonFirst => "Use `summon` to bring the bot into your voice channel first".to_owned(),
+            Reply::Reconnected => "Reconnected to voice".to_owned(),
+            Reply::ReconnectFailed => "Couldn't rejoin the voice channel".to_owned(),
+            Reply::NotSameVc => "You must be in the same voice channel to use this command.".to_owned(),
+            Reply::ConnectedElsewhere(channel) => format!("I'm already playing in {}, join it to add songs", channel),
+            Reply::OtherInstance => "⚠️ Another instance of this bot is already in that channel, playing over it would garble both".to_owned(),
+            Reply::AtCapacity => "The bot is at capacity, try again later".to_owned(),
+            Reply::NoMatches => "No matches".to_owned(),
+            Reply::SpotifyUnavailable => "That Spotify track is unavailable".to_owned(),
+            Reply::SpotifyCollection => "Spotify albums and playlists aren't supported, link a track instead".to_owned(),
+            Reply::QueriesAdded(found, failed) => format!("Found {} of {} songs, {} had no matches", found, found + failed, failed),
+            Reply::QueriesDropped(dropped, max) => format!("Only the first {} lines were searched, {} were left out", max, dropped),
+            Reply::QuotaExceeded => "The bot has hit its daily YouTube search limit — try again tomorrow or paste a direct link".to_owned(),
+            Reply::SourceError => "Error sourcing ffmpeg".to_owned(),
+            Reply::Playing(title) => format!("**Playing** 🎶 `{}` - Now!", title),
+            Reply::PlayingFor(title, requester) => format!("**Playing** 🎶 `{}` - Now! {}", title, requester),
+            Reply::StreamLost(title) => format!("Lost the stream for `{}`, skipping to the next song", title),
+            Reply::PlayUsage => "Usage: `play <song name or link>`, or attach an audio file".to_owned(),
+            Reply::ClipUsage => "Usage: `play <link> <start> [end]`, ex. `play <link> 0:30 1:15`. The end must be after the start".to_owned(),
+            Reply::QueueUsage => "Usage: `queue [--grouped] [page]` or `queue [--grouped] jump <position>`".to_owned(),
+            Reply::QueueJumpUsage => "Usage: `queue jump <position>`".to_owned(),
+            Reply::QueueFindUsage => "Usage: `queuefind <text>`".to_owned(),
+            Reply::NoQueueMatches(text) => format!("No queued songs match `{}`", text),
+            Reply::JumpToUsage => "Usage: `jumpto <song name>`".to_owned(),
+            Reply::JumpedTo(title) => format!("Jumping to `{}`", title),
+            Reply::LiveQueueStopped => "Stopped updating the live queue".to_owned(),
+            Reply::Paused => "Paused".to_owned(),
+            Reply::Resumed => "Resumed".to_owned(),
+            Reply::SkippedTo(title) => format!("Skipped! Now playing: `{}`", title),
+            Reply::SkippedEmpty => "Skipped! Queue is empty".to_owned(),
+            Reply::SkipWait(1) => "This song can be skipped in 1 second".to_owned(),
+            Reply::SkipWait(seconds) => format!("This song can be skipped in {} seconds", seconds),
+            Reply::SkipDelay(0) => "Songs can be skipped right away".to_owned(),
+            Reply::SkipDelay(seconds) => format!("Songs have to play for {} seconds before non-DJs can skip them", seconds),
+            Reply::SkipDelayUsage => "Usage: `skipdelay [seconds]`, up to 600. 0 turns it off".to_owned(),
+            Reply::IdleTimeout(1) => "The bot leaves voice after 1 minute with nothing playing".to_owned(),
+            Reply::IdleTimeout(minutes) => format!("The bot leaves voice after {} minutes with nothing playing", minutes),
+            Reply::IdleTimeoutOff => "The bot stays in voice until it's told to leave".to_owned(),
+            Reply::IdleTimeoutUsage => "Usage: `idletimeout [minutes]`, up to 1440. 0 keeps the bot in voice".to_owned(),
+            Reply::SearchUsage => "Usage: `search <song name>`".to_owned(),
+            Reply::SelectionTimedOut => "Selection timed out".to_owned(),
+            Reply::SelectionCancelled => "Selection cancelled".to_owned(),
+            Reply::SelectionTimeout(seconds) => format!("Search results can be picked for {} seconds", seconds),
+            Reply::SelectionTimeoutUsage => "Usage: `selecttimeout [seconds]`, from 5 to 300".to_owned(),
+            Reply::NoSearchHistory => "You haven't searched for anything yet".to_owned(),
+            Reply::SearchHistoryUsage => "Usage: `history [number]`".to_owned(),
+            Reply::MoveCooldown(1) => "The bot was just moved, try again in 1 second".to_owned(),
+            Reply::MoveCooldown(seconds) => format!("The bot was just moved, try again in {} seconds", seconds),
+            Reply::MoveCooldownSet(0) => "The bot can be moved between channels at any time".to_owned(),
+            Reply::MoveCooldownSet(seconds) => format!("The bot can be moved to another channel once every {} seconds", seconds),
+            Reply::MoveCooldownUsage => "Usage: `movecooldown [seconds]`, up to 600. 0 turns it off".to_owned(),
+            Reply::PlainTextOn => "Embeds will be sent as plain text".to_owned(),
+            Reply::PlainTextOff => "Embeds will be sent as embeds when the bot has the Embed Links permission".to_owned(),
+            Reply::PlainTextUsage => "Usage: `plaintext [on|off]`".to_owned(),
+            Reply::PingRequesterOn => "Whoever queued a song will be mentioned when it starts".to_owned(),
+            Reply::PingRequesterOff => "Songs will start without mentioning whoever queued them".to_owned(),
+            Reply::PingRequesterUsage => "Usage: `pingrequester [on|off]`".to_owned(),
+            Reply::ReactionsOn => "Skipping, stopping, pausing and resuming will be confirmed with a reaction".to_owned(),
+            Reply::ReactionsOff => "Skipping, stopping, pausing and resuming will be confirmed with a message".to_owned(),
+            Reply::ReactionsUsage => "Usage: `reactions [on|off]`".to_owned(),
+            Reply::QueueEmpty => "The queue is empty".to_owned(),
+            Reply::PositionRange(len) => format!("Position must be between 1 and {}", len),
+            Reply::Moved(title, position) => format!("Moved `{}` to position {}", title, position),
+            Reply::MoveUsage => "Usage: `move <from> <to>` or `move <position> up|down [amount]`".to_owned(),
+            Reply::RemoveUsage => "Usage: `remove <position>`".to_owned(),
+            Reply::Removed(title) => format!("Removed `{}`", title),
+            Reply::DuplicatesRemoved(1) => "Removed 1 duplicate".to_owned(),
+            Reply::DuplicatesRemoved(count) => format!("Removed {} duplicates", count),
+            Reply::MovedToFront(0) => "You have no songs in the queue".to_owned(),
+            Reply::MovedToFront(1) => "Moved your song to the front".to_owned(),
+            Reply::MovedToFront(count) => format!("Moved your {} songs to the front", count),
+            Reply::NotYourSong => "You need the DJ role to change songs queued by someone else".to_owned(),
+            Reply::Swapped(title) => format!("Playing `{}` now, the current song continues after it", title),
+            Reply::Volume(volume) => format!("🔊 Volume is {}%", volume),
+            Reply::VolumeRange => "Volume must be between 0 and 200".to_owned(),
+            Reply::VolumeSet(volume) => format!("🔊 Volume set to {}%", volume),
+            Reply::Undone => "Undid the last queue change".to_owned(),
+            Reply::NothingToUndo => "Nothing to undo".to_owned(),
+            Reply::ReplacedWith(title) => format!("Replaced it with `{}`", title),
+            Reply::NothingToReplace => "You haven't queued anything to replace".to_owned(),
+            Reply::NoOtherResults => "There are no other results for that song, try `wrongsong <search>`".to_owned(),
+            Reply::Looping => "🔂 Looping the current song".to_owned(),
+            Reply::LoopingTimes(count) => format!("🔂 Looping the current song {} more times", count),
+            Reply::LoopStopped => "Stopped looping".to_owned(),
+            Reply::LoopUsage => "Usage: `loop`, `loop <count>` or `loop off`".to_owned(),
+            Reply::AutoplayEnabled => "Autoplay enabled".to_owned(),
+            Reply::AutoplayDisabled => "Autoplay disabled".to_owned(),
+            Reply::LiveProgressEnabled => "Now playing will update as the song plays".to_owned(),
+            Reply::LiveProgressDisabled => "Now playing will stay static".to_owned(),
+            Reply::ClearedCurrent => "Cleared the current song".to_owned(),
+            Reply::ClearedUpcoming(0) => "There are no upcoming songs to clear".to_owned(),
+            Reply::ClearedUpcoming(1) => "Cleared 1 upcoming song, the current song keeps playing".to_owned(),
+            Reply::ClearedUpcoming(count) => format!("Cleared {} upcoming songs, the current song keeps playing", count),
+            Reply::ClearedAll(1) => "Stopped the current song and cleared 1 upcoming song".to_owned(),
+            Reply::ClearedAll(count) => format!("Stopped the current song and cleared {} upcoming songs", count),
+            Reply::ClearUsage => "Usage: `clear [upcoming|all]`, all also stops the current song".to_owned(),
+            Reply::Equalizer(name, presets) => format!("Equalizer is set to `{}`. Presets: {}", name, presets),
+            Reply::UnknownPreset(presets) => format!("Unknown preset, try one of: {}", presets),
+            Reply::EqualizerSet(name) => format!("🎚️ Equalizer set to `{}`", name),
+            Reply::LeaveGuildUsage => "Usage: `leaveguild <id>`".to_owned(),
+            Reply::LeftGuild(id) => format!("Left guild `{}`", id),
+            Reply::PausedAll(1) => "Paused playback in 1 server".to_owned(),
+            Reply::PausedAll(count) => format!("Paused playback in {} servers", count),
+            Reply::StoppedAll(1) => "Stopped playback in 1 server".to_owned(),
+            Reply::StoppedAll(count) => format!("Stopped playback in {} servers", count),
+            Reply::Saved(name) => format!("Saved as `{}`, play it with `file:{}`", name, name),
+            Reply::AlreadySaved => "This song is already a saved file".to_owned(),
+            Reply::SaveFailed => "Couldn't download this song".to_owned(),
+            Reply::CantDisable => "That command can't be disabled".to_owned(),
+            Reply::Disabled(name) => format!("Disabled `{}`", name),
+            Reply::Enabled(name) => format!("Enabled `{}`", name),
+            Reply::UnknownCommand(name) => format!("Could not find: `{}`.", name),
+            Reply::Prefix(prefix) => format!("The prefix here is `{}`", prefix),
+            Reply::PrefixReset(prefix) => format!("Prefix reset to `{}`", prefix),
+            Reply::PrefixSet(prefix) => format!("Prefix set to `{}`", prefix),
+            Reply::PrefixSpaces => "The prefix can't contain spaces".to_owned(),
+            Reply::PrefixHint(prefix) => format!("My prefix here is `{0}`. Try `{0}play <song>` or `{0}help` to see all commands", prefix),
+            Reply::Language(name, languages) => format!("The language here is {}. Languages: {}", name, languages),
+            Reply::UnknownLanguage(languages) => format!("Unknown language, try one of: {}", languages),
+            Reply::LanguageSet => "Language set to English".to_owned(),
+            Reply::AutoJoinOn => "`play` joins your voice channel by itself".to_owned(),
+            Reply::AutoJoinOff => "`play` only works after the bot is summoned".to_owned(),
+            Reply::AutoJoinUsage => "Usage: `autojoin [on|off|reset]`".to_owned(),
+            Reply::SameChannelOn => "Playback can only be controlled from the bot's voice channel".to_owned(),
+            Reply::SameChannelOff => "Playback can be controlled from anywhere in the server".to_owned(),
+            Reply::SameChannelUsage => "Usage: `samechannel [on|off]`".to_owned(),
+            Reply::StickyOn(channel) => format!("Now playing will stay at the bottom of {}", channel),
+            Reply::StickyOff => "Now playing is no longer kept at the bottom".to_owned(),
+            Reply::StickyUsage => "Usage: `stickynp [on|off]`, use it in the channel to keep now playing in".to_owned(),
+            Reply::CommandChannelOnly(channel) => format!("Use music commands in {}", channel),
+            Reply::CommandChannelSet(channel) => format!("Music commands now only work in {}", channel),
+            Reply::CommandChannelCleared => "Music commands work in every channel again".to_owned(),
+            Reply::CommandChannelUsage => "Usage: `musicchannel [on|off]`, use it in the channel music commands should be limited to".to_owned(),
+            Reply::CommandDisabled => "That command is disabled here".to_owned(),
+            Reply::NeedDj => "You need the DJ role to use this command".to_owned(),
+            Reply::MissingPermission(permissions) => format!("You need the {} permission to use this command", permissions),
+            Reply::OwnersOnly => "Only the bot's owners can use this command".to_owned(),
+            Reply::Busy => "The bot is busy, try again shortly".to_owned(),
+            Reply::MixUnsupported => "YouTube mixes can't be queued, so only the linked video was added and autoplay was turned on".to_owned(),
+            Reply::PlaylistLimit(added, total) => format!("Added first {} of {} tracks (limit reached)", added, total),
+            Reply::AlbumLimit(added) => format!("Added first {} tracks (limit reached)", added),
+        }
+    }
+
+    fn spanish(&self) -> String {
+        match self {
+            Reply::Joined(channel) => format!("Conectado a {}", channel),
+            Reply::CantConnect => "No tengo permiso para conectarme a tu canal de voz".to_owned(),
+            Reply::CantSpeak => "No tengo permiso para hablar en tu canal de voz".to_owned(),
+            Reply::ChannelFull => "Tu canal de voz está lleno".to_owned(),
+            Reply::JoinTimedOut => "Discord no respondió a tiempo, inténtalo de nuevo en un momento".to_owned(),
+            Reply::VoiceServerFailed => "No se pudo conectar al servidor de voz de la región de este canal, inténtalo de nuevo o cambia la región".to_owned(),
+            Reply::JoinFailed => "No pude unirme a tu canal de voz".to_owned(),
+            Reply::MustBeInVc => "Debes estar en un canal de voz para usar este comando".to_owned(),
+            Reply::NothingPlaying => "No se está reproduciendo nada".to_owned(),
+            Reply::ServerOnly => "Este comando solo se puede usar en un servidor".to_owned(),
+            Reply::ServerNotCached => "No se pudo cargar este servidor, inténtalo de nuevo en un momento".to_owned(),
+            Reply::NotInVc => "No estoy en un canal de voz".to_owned(),
+            Reply::SummonFirst => "Usa `summon` para traer el bot a tu canal de voz primero".to_owned(),
+            Reply::Reconnected => "Reconectado al canal de voz".to_owned(),
+            Reply::ReconnectFailed => "No se pudo volver a entrar al canal de voz".to_owned(),
+            Reply::NotSameVc => "Debes estar en el mismo canal de voz para usar este comando.".to_owned(),
+            Reply::ConnectedElsewhere(channel) => format!("Ya estoy reproduciendo en {}, únete para añadir canciones", channel),
+            Reply::OtherInstance => "⚠️ Ya hay otra instancia de este bot en ese canal, reproducir encima distorsionaría a ambas".to_owned(),
+            Reply::AtCapacity => "El bot está al máximo de su capacidad, inténtalo más tarde".to_owned(),
+            Reply::NoMatches => "Sin resultados".to_owned(),
+            Reply::SpotifyUnavailable => "Esa canción de Spotify no está disponible".to_owned(),
+            Reply::SpotifyCollection => "Los álbumes y listas de Spotify no son compatibles, enlaza una canción".to_owned(),
+            Reply::QueriesAdded(found, failed) => format!("Se encontraron {} de {} canciones, {} sin resultados", found, found + failed, failed),
+            Reply::QueriesDropped(dropped, max) => format!("Solo se buscaron las primeras {} líneas, {} se omitieron", max, dropped),
+            Reply::QuotaExceeded => "El bot alcanzó su límite diario de búsquedas en YouTube — inténtalo mañana o pega un enlace directo".to_owned(),
+            Reply::SourceError => "Error al iniciar ffmpeg".to_owned(),
+            Reply::Playing(title) => format!("**Reproduciendo** 🎶 `{}` - ¡Ahora!", title),
+            Reply::PlayingFor(title, requester) => format!("**Reproduciendo** 🎶 `{}` - ¡Ahora! {}", title, requester),
+            Reply::StreamLost(title) => format!("Se perdió la transmisión de `{}`, pasando a la siguiente canción", title),
+            Reply::PlayUsage => "Uso: `play <nombre o enlace de la canción>`, o adjunta un archivo de audio".to_owned(),
+            Reply::ClipUsage => "Uso: `play <enlace> <inicio> [fin]`, ej. `play <enlace> 0:30 1:15`. El fin debe ser después del inicio".to_owned(),
+            Reply::QueueUsage => "Uso: `queue [--grouped] [página]` o `queue [--grouped] jump <posición>`".to_owned(),
+            Reply::QueueJumpUsage => "Uso: `queue jump <posición>`".to_owned(),
+            Reply::QueueFindUsage => "Uso: `queuefind <texto>`".to_owned(),
+            Reply::NoQueueMatches(text) => format!("Ninguna canción en la cola coincide con `{}`", text),
+            Reply::JumpToUsage => "Uso: `jumpto <nombre de la canción>`".to_owned(),
+            Reply::JumpedTo(title) => format!("Saltando a `{}`", title),
+            Reply::LiveQueueStopped => "La cola en vivo ya no se actualizará".to_owned(),
+            Reply::Paused => "En pausa".to_owned(),
+            Reply::Resumed => "Reanudado".to_owned(),
+            Reply::SkippedTo(title) => format!("¡Saltada! Reproduciendo: `{}`", title),
+            Reply::SkippedEmpty => "¡Saltada! La cola está vacía".to_owned(),
+            Reply::SkipWait(1) => "Esta canción se puede saltar en 1 segundo".to_owned(),
+            Reply::SkipWait(seconds) => format!("Esta canción se puede saltar en {} segundos", seconds),
+            Reply::SkipDelay(0) => "Las canciones se pueden saltar de inmediato".to_owned(),
+            Reply::SkipDelay(seconds) => format!("Las canciones deben sonar {} segundos antes de que quienes no son DJ puedan saltarlas", seconds),
+            Reply::SkipDelayUsage => "Uso: `skipdelay [segundos]`, hasta 600. 0 lo desactiva".to_owned(),
+            Reply::IdleTimeout(1) => "El bot sale del canal de voz tras 1 minuto sin reproducir nada".to_owned(),
+            Reply::IdleTimeout(minutes) => format!("El bot sale del canal de voz tras {} minutos sin reproducir nada", minutes),
+            Reply::IdleTimeoutOff => "El bot se queda en el canal de voz hasta que se le pida salir".to_owned(),
+            Reply::IdleTimeoutUsage => "Uso: `idletimeout [minutos]`, hasta 1440. 0 mantiene al bot en el canal de voz".to_owned(),
+            Reply::SearchUsage => "Uso: `search <nombre de la canción>`".to_owned(),
+            Reply::SelectionTimedOut => "Se acabó el tiempo para elegir".to_owned(),
+            Reply::SelectionCancelled => "Selección cancelada".to_owned(),
+            Reply::SelectionTimeout(seconds) => format!("Los resultados de búsqueda se pueden elegir durante {} segundos", seconds),
+            Reply::SelectionTimeoutUsage => "Uso: `selecttimeout [segundos]`, de 5 a 300".to_owned(),
+            Reply::NoSearchHistory => "Todavía no has buscado nada".to_owned(),
+            Reply::SearchHistoryUsage => "Uso: `history [número]`".to_owned(),
+            Reply::MoveCooldown(1) => "El bot se acaba de mover, inténtalo de nuevo en 1 segundo".to_owned(),
+            Reply::MoveCooldown(seconds) => format!("El bot se acaba de mover, inténtalo de nuevo en {} segundos", seconds),
+            Reply::MoveCooldownSet(0) => "El bot se puede mover entre canales en cualquier momento".to_owned(),
+            Reply::MoveCooldownSet(seconds) => format!("El bot se puede mover a otro canal una vez cada {} segundos", seconds),
+            Reply::MoveCooldownUsage => "Uso: `movecooldown [segundos]`, hasta 600. 0 lo desactiva".to_owned(),
+            Reply::PlainTextOn => "Los embeds se enviarán como texto".to_owned(),
+            Reply::PlainTextOff => "Los embeds se enviarán como embeds cuando el bot tenga el permiso Insertar enlaces".to_owned(),
+            Reply::PlainTextUsage => "Uso: `plaintext [on|off]`".to_owned(),
+            Reply::PingRequesterOn => "Se mencionará a quien añadió una canción cuando empiece".to_owned(),
+            Reply::PingRequesterOff => "Las canciones empezarán sin mencionar a quien las añadió".to_owned(),
+            Reply::PingRequesterUsage => "Uso: `pingrequester [on|off]`".to_owned(),
+            Reply::ReactionsOn => "Saltar, detener, pausar y reanudar se confirmarán con una reacción".to_owned(),
+            Reply::ReactionsOff => "Saltar, detener, pausar y reanudar se confirmarán con un mensaje".to_owned(),
+            Reply::ReactionsUsage => "Uso: `reactions [on|off]`".to_owned(),
+            Reply::QueueEmpty => "La cola está vacía".to_owned(),
+            Reply::PositionRange(len) => format!("La posición debe estar entre 1 y {}", len),
+            Reply::Moved(title, position) => format!("`{}` movida a la posición {}", title, position),
+            Reply::MoveUsage => "Uso: `move <desde> <hasta>` o `move <posición> up|down [cantidad]`".to_owned(),
+            Reply::RemoveUsage => "Uso: `remove <posición>`".to_owned(),
+            Reply::Removed(title) => format!("Se quitó `{}`", title),
+            Reply::DuplicatesRemoved(1) => "Se quitó 1 duplicado".to_owned(),
+            Reply::DuplicatesRemoved(count) => format!("Se quitaron {} duplicados", count),
+            Reply::MovedToFront(0) => "No tienes canciones en la cola".to_owned(),
+            Reply::MovedToFront(1) => "Tu canción se movió al principio".to_owned(),
+            Reply::MovedToFront(count) => format!("Tus {} canciones se movieron al principio", count),
+            Reply::NotYourSong => "Necesitas el rol DJ para cambiar canciones que añadió otra persona".to_owned(),
+            Reply::Swapped(title) => format!("Reproduciendo `{}` ahora, la canción actual seguirá después", title),
+            Reply::Volume(volume) => format!("🔊 El volumen está al {}%", volume),
+            Reply::VolumeRange => "El volumen debe estar entre 0 y 200".to_owned(),
+            Reply::VolumeSet(volume) => format!("🔊 Volumen ajustado al {}%", volume),
+            Reply::Undone => "Se deshizo el último cambio de la cola".to_owned(),
+            Reply::NothingToUndo => "No hay nada que deshacer".to_owned(),
+            Reply::ReplacedWith(title) => format!("Se reemplazó por `{}`", title),
+            Reply::NothingToReplace => "No has añadido nada que reemplazar".to_owned(),
+            Reply::NoOtherResults => "No hay otros resultados para esa canción, prueba `wrongsong <búsqueda>`".to_owned(),
+            Reply::Looping => "🔂 Repitiendo la canción actual".to_owned(),
+            Reply::LoopingTimes(count) => format!("🔂 Repitiendo la canción actual {} veces más", count),
+            Reply::LoopStopped => "Ya no se repite".to_owned(),
+            Reply::LoopUsage => "Uso: `loop`, `loop <veces>` o `loop off`".to_owned(),
+            Reply::AutoplayEnabled => "Reproducción automática activada".to_owned(),
+            Reply::AutoplayDisabled => "Reproducción automática desactivada".to_owned(),
+            Reply::LiveProgressEnabled => "La canción actual se actualizará mientras suena".to_owned(),
+            Reply::LiveProgressDisabled => "La canción actual se mostrará sin actualizar".to_owned(),
+            Reply::ClearedCurrent => "Se quitó la canción actual".to_owned(),
+            Reply::ClearedUpcoming(0) => "No hay canciones en espera que quitar".to_owned(),
+            Reply::ClearedUpcoming(1) => "Se quitó 1 canción en espera, la canción actual sigue sonando".to_owned(),
+            Reply::ClearedUpcoming(count) => format!("Se quitaron {} canciones en espera, la canción actual sigue sonando", count),
+            Reply::ClearedAll(1) => "Se detuvo la canción actual y se quitó 1 canción en espera".to_owned(),
+            Reply::ClearedAll(count) => format!("Se detuvo la canción actual y se quitaron {} canciones en espera", count),
+            Reply::ClearUsage => "Uso: `clear [upcoming|all]`, all también detiene la canción actual".to_owned(),
+            Reply::Equalizer(name, presets) => format!("El ecualizador está en `{}`. Opciones: {}", name, presets),
+            Reply::UnknownPreset(presets) => format!("Opción desconocida, prueba una de: {}", presets),
+            Reply::EqualizerSet(name) => format!("🎚️ Ecualizador ajustado a `{}`", name),
+            Reply::LeaveGuildUsage => "Uso: `leaveguild <id>`".to_owned(),
+            Reply::LeftGuild(id) => format!("Salí del servidor `{}`", id),
+            Reply::PausedAll(1) => "Se pausó la reproducción en 1 servidor".to_owned(),
+            Reply::PausedAll(count) => format!("Se pausó la reproducción en {} servidores", count),
+            Reply::StoppedAll(1) => "Se detuvo la reproducción en 1 servidor".to_owned(),
+            Reply::StoppedAll(count) => format!("Se detuvo la reproducción en {} servidores", count),
+            Reply::Saved(name) => format!("Guardada como `{}`, reprodúcela con `file:{}`", name, name),
+            Reply::AlreadySaved => "Esta canción ya es un archivo guardado".to_owned(),
+            Reply::SaveFailed => "No se pudo descargar esta canción".to_owned(),
+            Reply::CantDisable => "Ese comando no se puede desactivar".to_owned(),
+            Reply::Disabled(name) => format!("`{}` desactivado", name),
+            Reply::Enabled(name) => format!("`{}` activado", name),
+            Reply::UnknownCommand(name) => format!("No se encontró: `{}`.", name),
+            Reply::Prefix(prefix) => format!("El prefijo aquí es `{}`", prefix),
+            Reply::PrefixReset(prefix) => format!("Prefijo restablecido a `{}`", prefix),
+            Reply::PrefixSet(prefix) => format!("Prefijo cambiado a `{}`", prefix),
+            Reply::PrefixSpaces => "El prefijo no puede contener espacios".to_owned(),
+            Reply::PrefixHint(prefix) => format!("Mi prefijo aquí es `{0}`. Prueba `{0}play <canción>` o `{0}help` para ver todos los comandos", prefix),
+            Reply::Language(name, languages) => format!("El idioma aquí es {}. Idiomas: {}", name, languages),
+            Reply::UnknownLanguage(languages) => format!("Idioma desconocido, prueba uno de: {}", languages),
+            Reply::LanguageSet => "Idioma cambiado a español".to_owned(),
+            Reply::AutoJoinOn => "`play` se une a tu canal de voz por sí solo".to_owned(),
+            Reply::AutoJoinOff => "`play` solo funciona después de usar `summon`".to_owned(),
+            Reply::AutoJoinUsage => "Uso: `autojoin [on|off|reset]`".to_owned(),
+            Reply::SameChannelOn => "La reproducción solo se puede controlar desde el canal de voz del bot".to_owned(),
+            Reply::SameChannelOff => "La reproducción se puede controlar desde cualquier lugar del servidor".to_owned(),
+            Reply::SameChannelUsage => "Uso: `samechannel [on|off]`".to_owned(),
+            Reply::StickyOn(channel) => format!("La canción actual se mantendrá al final de {}", channel),
+            Reply::StickyOff => "La canción actual ya no se mantiene al final".to_owned(),
+            Reply::StickyUsage => "Uso: `stickynp [on|off]`, úsalo en el canal donde mostrar la canción actual".to_owned(),
+            Reply::CommandChannelOnly(channel) => format!("Usa los comandos de música en {}", channel),
+            Reply::CommandChannelSet(channel) => format!("Los comandos de música ahora solo funcionan en {}", channel),
+            Reply::CommandChannelCleared => "Los comandos de música vuelven a funcionar en todos los canales".to_owned(),
+            Reply::CommandChannelUsage => "Uso: `musicchannel [on|off]`, úsalo en el canal al que limitar los comandos de música".to_owned(),
+            Reply::CommandDisabled => "Ese comando está desactivado aquí".to_owned(),
+            Reply::NeedDj => "Necesitas el rol DJ para usar este comando".to_owned(),
+            Reply::MissingPermission(permissions) => format!("Necesitas el permiso {} para usar este comando", permissions),
+            Reply::OwnersOnly => "Solo los dueños del bot pueden usar este comando".to_owned(),
+            Reply::Busy => "El bot está ocupado, inténtalo de nuevo en breve".to_owned(),
+            Reply::MixUnsupported => "Los mixes de YouTube no se pueden añadir a la cola, así que solo se añadió el video y se activó la reproducción automática".to_owned(),
+            Reply::PlaylistLimit(added, total) => format!("Se añadieron las primeras {} de {} canciones (límite alcanzado)", added, total),
+            Reply::AlbumLimit(added) => format!("Se añadieron las primeras {} canciones (límite alcanzado)", added),
+        }
+    }
+}
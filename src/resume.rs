@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Chapter, Song, SongSource};
+
+// A queued song as saved to disk, without its track handle
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedSong {
+    title: String,
+    artist: String,
+    author: String,
+    duration: Duration,
+    source: SongSource,
+    // the part of the song that plays, for clips
+    start: Option<Duration>,
+    end: Option<Duration>,
+    spotify_id: Option<String>,
+    thumbnail_url: Option<String>,
+    chapters: Vec<Chapter>,
+    live: bool,
+}
+
+impl SavedSong {
+    // None for attachments, their links expire so they couldn't be played after a restart
+    pub(crate) fn from_song(song: &Song) -> Option<SavedSong> {
+        if let SongSource::Direct { .. } = song.source {
+            return None;
+        }
+        Some(SavedSong {
+            title: song.title.clone(),
+            artist: song.artist.clone(),
+            author: song.author.clone(),
+            duration: song.duration,
+            source: song.source.clone(),
+            start: song.start,
+            end: song.end,
+            spotify_id: song.spotify_id.clone(),
+            thumbnail_url: song.thumbnail_url.clone(),
+            chapters: song.chapters.clone(),
+            live: song.live,
+        })
+    }
+
+    pub(crate) fn into_song(self) -> Song {
+        Song {
+            start: self.start,
+            end: self.end,
+            spotify_id: self.spotify_id,
+            thumbnail_url: self.thumbnail_url,
+            chapters: self.chapters,
+            live: self.live,
+            ..Song::new(self.title, self.artist, self.author, self.duration, self.source)
+        }
+    }
+}
+
+// What a server was playing, so it can pick up where it left off after a restart
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedQueue {
+    pub(crate) voice_channel: u64,
+    // where songs are announced
+    pub(crate) text_channel: u64,
+    // how far into the first song playback was
+    pub(crate) position: Duration,
+    // the now playing song first, then the queue
+    pub(crate) songs: Vec<SavedSong>,
+}
+
+// Reads the queues saved by the last run, keyed by guild id. Empty if there are none
+pub(crate) fn load(path: &Path) -> HashMap<u64, SavedQueue> {
+    match std::fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|why| {
+            println!("Error parsing saved queues, not resuming: {:?}", why);
+            HashMap::new()
+        }),
+        Err(_) => HashMap::new(),
+    }
+}
+
+pub(crate) async fn save(path: &Path, queues: &HashMap<u64, SavedQueue>) {
+    match serde_json::to_vec(queues) {
+        Ok(bytes) => {
+            if let Err(why) = tokio::fs::write(path, bytes).await {
+                println!("Error saving queues: {:?}", why);
+            }
+        }
+        Err(why) => println!("Error serializing queues: {:?}", why),
+    }
+}
@@ -0,0 +1,201 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Client;
+use serde::Deserialize;
+use serenity::async_trait;
+use serenity::prelude::TypeMapKey;
+
+use crate::{ApiAccess, Song, YouTubeVideo, parse_chapters};
+
+// Where searches and YouTube video lookups go, picked with the SEARCH_PROVIDER env var.
+// Authors are left empty for the caller to fill in.
+#[async_trait]
+pub(crate) trait SearchProvider: Send + Sync {
+    // Songs matching the query, best match first
+    async fn search(&self, query: &str) -> Result<Vec<Song>, QuotaExceeded>;
+
+    // Looks up a YouTube video by id
+    async fn video(&self, id: &str) -> Result<Option<Song>, QuotaExceeded>;
+
+    // Up to MAX_PLAYLIST_SIZE songs from a YouTube playlist
+    async fn playlist(&self, id: &str) -> Result<Playlist, QuotaExceeded>;
+
+    // How many people are watching a YouTube livestream right now, if the provider knows
+    async fn live_viewers(&self, _id: &str) -> Option<u64> {
+        None
+    }
+}
+
+// The YouTube Data API's daily quota ran out, searches fail until it resets at midnight Pacific time.
+// Other failures are logged and treated as no results.
+pub(crate) struct QuotaExceeded;
+
+pub(crate) struct Playlist {
+    pub(crate) name: Option<String>,
+    pub(crate) songs: Vec<Song>,
+    // length of the whole playlist, can be more than songs when it was cut off
+    pub(crate) total: usize,
+}
+
+pub(crate) struct SearchProviderKey;
+impl TypeMapKey for SearchProviderKey {
+    type Value = Arc<dyn SearchProvider>;
+}
+
+// Uses the YouTube Data API, each search costs 100 units of the daily quota
+pub(crate) struct YouTubeSearch {
+    pub(crate) api_access: Arc<ApiAccess>,
+}
+
+#[async_trait]
+impl SearchProvider for YouTubeSearch {
+    async fn search(&self, query: &str) -> Result<Vec<Song>, QuotaExceeded> {
+        let results = self.api_access.search_yt(query).await?;
+        let ids = results.items.into_iter().map(|video| video.id.video_id).collect::<Vec<_>>();
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // search results don't include durations, so look the videos up in one request
+        Ok(self.api_access.get_videos(&ids)
+            .await?
+            .iter()
+            .map(|video| video.as_song(String::new()))
+            .collect())
+    }
+
+    async fn video(&self, id: &str) -> Result<Option<Song>, QuotaExceeded> {
+        Ok(self.api_access.get_videos(&[id.to_owned()])
+            .await?
+            .first()
+            .map(|video| video.as_song(String::new())))
+    }
+
+    async fn playlist(&self, id: &str) -> Result<Playlist, QuotaExceeded> {
+        let (videos, total) = self.api_access.get_playlist_videos(id).await?;
+
+        Ok(Playlist {
+            name: self.api_access.get_playlist_name(id).await?,
+            songs: videos.iter().map(|video| video.as_song(String::new())).collect(),
+            total,
+        })
+    }
+
+    async fn live_viewers(&self, id: &str) -> Option<u64> {
+        self.api_access.get_videos(&[id.to_owned()]).await.ok()?.first()?.viewers
+    }
+}
+
+// Uses an Invidious instance, which needs no API key and has no quota
+pub(crate) struct InvidiousSearch {
+    // ex. "https://invidious.example.com"
+    pub(crate) base_url: String,
+    pub(crate) http: Client,
+    pub(crate) max_playlist_size: usize,
+}
+
+#[derive(Deserialize)]
+struct InvidiousVideo {
+    title: String,
+    #[serde(rename="videoId")]
+    video_id: String,
+    author: String,
+    #[serde(rename="lengthSeconds")]
+    length_seconds: u64,
+    // only the full video lookup has chapters, search results cut the description short
+    #[serde(default)]
+    description: String,
+    #[serde(rename="liveNow", default)]
+    live_now: bool,
+}
+
+impl InvidiousVideo {
+    fn as_song(&self) -> Song {
+        YouTubeVideo {
+            name: self.title.clone(),
+            channel: self.author.clone(),
+            duration: Duration::from_secs(self.length_seconds),
+            id: self.video_id.clone(),
+            chapters: parse_chapters(&self.description),
+            live: self.live_now,
+            // Invidious doesn't report concurrent viewers
+            viewers: None,
+        }.as_song(String::new())
+    }
+}
+
+impl InvidiousSearch {
+    async fn get<T: for<'de> Deserialize<'de>>(&self, path: &str, query: &[(&str, &str)]) -> Option<T> {
+        let response = self.http.get(format!("{}/api/v1/{}", self.base_url, path))
+            .query(query)
+            .send()
+            .await;
+
+        match response {
+            Ok(response) if response.status().is_success() => match response.json::<T>().await {
+                Ok(value) => Some(value),
+                Err(why) => {
+                    println!("Error parsing Invidious response for {}: {:?}", path, why);
+                    None
+                }
+            },
+            Ok(response) => {
+                println!("Invidious returned {} for {}", response.status(), path);
+                None
+            }
+            Err(why) => {
+                println!("Failed to access Invidious: {:?}", why);
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl SearchProvider for InvidiousSearch {
+    async fn search(&self, query: &str) -> Result<Vec<Song>, QuotaExceeded> {
+        // results can also be channels or playlists, skip anything that isn't a video
+        let results = self.get::<Vec<serde_json::Value>>("search", &[("q", query), ("type", "video")]).await.unwrap_or_default();
+
+        Ok(results.into_iter()
+            .filter_map(|result| serde_json::from_value::<InvidiousVideo>(result).ok())
+            .map(|video| video.as_song())
+            .collect())
+    }
+
+    async fn video(&self, id: &str) -> Result<Option<Song>, QuotaExceeded> {
+        Ok(self.get::<InvidiousVideo>(&format!("videos/{}", id), &[]).await.map(|video| video.as_song()))
+    }
+
+    async fn playlist(&self, id: &str) -> Result<Playlist, QuotaExceeded> {
+        #[derive(Deserialize)]
+        struct InvidiousPlaylist {
+            title: String,
+            #[serde(rename="videoCount")]
+            video_count: usize,
+            videos: Vec<InvidiousVideo>,
+        }
+
+        let mut songs = Vec::new();
+        let mut name = None;
+        let mut total = 0;
+        let mut page = 1;
+
+        while songs.len() < self.max_playlist_size {
+            let playlist = match self.get::<InvidiousPlaylist>(&format!("playlists/{}", id), &[("page", &page.to_string())]).await {
+                Some(playlist) if !playlist.videos.is_empty() => playlist,
+                _ => break,
+            };
+
+            name = Some(playlist.title);
+            total = playlist.video_count;
+            songs.extend(playlist.videos.iter().map(|video| video.as_song()));
+            page += 1;
+        }
+        songs.truncate(self.max_playlist_size);
+        let total = total.max(songs.len());
+
+        Ok(Playlist { name, songs, total })
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serenity::model::id::GuildId;
+use serenity::prelude::TypeMapKey;
+use tokio::sync::RwLock;
+
+use crate::messages::Locale;
+
+// Per-guild configuration that is saved to disk so it survives restarts
+#[derive(Serialize, Deserialize)]
+pub(crate) struct GuildSettings {
+    // primary names of commands that can't be used in the guild
+    #[serde(default)]
+    pub(crate) disabled_commands: HashSet<String>,
+    // replaces COMMAND_PREFIX in the guild when set
+    #[serde(default)]
+    pub(crate) prefix: Option<String>,
+    // message that is kept up to date with the queue instead of posting new ones
+    #[serde(default)]
+    pub(crate) live_queue: Option<LiveQueue>,
+    // percent, applied to every song that starts
+    #[serde(default = "default_volume")]
+    pub(crate) volume: u32,
+    // language of the bot's replies
+    #[serde(default)]
+    pub(crate) locale: Locale,
+    // replaces AUTO_JOIN in the guild when set
+    #[serde(default)]
+    pub(crate) auto_join: Option<bool>,
+    // whether playback commands need the author in the bot's voice channel
+    #[serde(default = "default_true")]
+    pub(crate) same_channel_only: bool,
+    // channel where the now playing embed is reposted under new messages
+    #[serde(default)]
+    pub(crate) sticky_channel: Option<u64>,
+    // seconds a song has to play before non-DJs can skip it, 0 for no wait
+    #[serde(default)]
+    pub(crate) skip_delay: u64,
+    // the only channel music commands can be used in, any channel when unset
+    #[serde(default)]
+    pub(crate) command_channel: Option<u64>,
+    // minutes the bot stays in voice with nothing playing, 0 to stay forever. Replaces IDLE_TIMEOUT when set
+    #[serde(default)]
+    pub(crate) idle_timeout: Option<u64>,
+    // seconds search waits for a result to be picked
+    #[serde(default = "default_selection_timeout")]
+    pub(crate) selection_timeout: u64,
+    // seconds before summon can move the bot to another voice channel again
+    #[serde(default = "default_move_cooldown")]
+    pub(crate) move_cooldown: u64,
+    // whether embeds are sent as plain text, for servers that don't want them
+    #[serde(default)]
+    pub(crate) plain_text: bool,
+    // whether now playing mentions whoever queued the song
+    #[serde(default)]
+    pub(crate) ping_requester: bool,
+    // whether playback confirmations like skip are reactions on the command instead of messages
+    #[serde(default)]
+    pub(crate) reactions: bool,
+}
+
+impl Default for GuildSettings {
+    fn default() -> GuildSettings {
+        GuildSettings {
+            disabled_commands: HashSet::new(),
+            prefix: None,
+            live_queue: None,
+            volume: default_volume(),
+            locale: Locale::default(),
+            auto_join: None,
+            same_channel_only: true,
+            sticky_channel: None,
+            skip_delay: 0,
+            command_channel: None,
+            idle_timeout: None,
+            selection_timeout: default_selection_timeout(),
+            move_cooldown: default_move_cooldown(),
+            plain_text: false,
+            ping_requester: false,
+            reactions: false,
+        }
+    }
+}
+
+fn default_volume() -> u32 {
+    100
+}
+
+fn default_selection_timeout() -> u64 {
+    30
+}
+
+fn default_move_cooldown() -> u64 {
+    10
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct LiveQueue {
+    pub(crate) channel_id: u64,
+    pub(crate) message_id: u64,
+}
+
+pub(crate) struct GuildSettingsKey;
+impl TypeMapKey for GuildSettingsKey {
+    type Value = Arc<GuildSettingsStore>;
+}
+
+pub(crate) struct GuildSettingsStore {
+    path: PathBuf,
+    // COMMAND_PREFIX, used by guilds without their own prefix and in DMs
+    default_prefix: String,
+    // AUTO_JOIN, used by guilds that haven't chosen
+    default_auto_join: bool,
+    // IDLE_TIMEOUT, used by guilds that haven't chosen
+    default_idle_timeout: Duration,
+    // keyed by the raw guild id because JSON object keys must be strings
+    map: RwLock<HashMap<u64, GuildSettings>>,
+}
+
+impl GuildSettingsStore {
+    // Loads the settings file, or starts empty if it doesn't exist yet
+    pub(crate) fn load(path: PathBuf, default_prefix: String, default_auto_join: bool, default_idle_timeout: Duration) -> GuildSettingsStore {
+        let map = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).expect("Error parsing guild settings file"),
+            Err(_) => HashMap::new(),
+        };
+
+        GuildSettingsStore { path, default_prefix, default_auto_join, default_idle_timeout, map: RwLock::new(map) }
+    }
+
+    // The prefix commands need in a guild, or in DMs when there is no guild
+    pub(crate) async fn prefix(&self, guild_id: Option<GuildId>) -> String {
+        let prefix = match guild_id {
+            Some(guild_id) => self.get(guild_id, |settings| settings.prefix.clone()).await,
+            None => None,
+        };
+
+        prefix.unwrap_or_else(|| self.default_prefix.clone())
+    }
+
+    // Whether play joins the author's voice channel, or needs the bot to be summoned first
+    pub(crate) async fn auto_join(&self, guild_id: GuildId) -> bool {
+        self.get(guild_id, |settings| settings.auto_join).await.unwrap_or(self.default_auto_join)
+    }
+
+    // How long the bot waits in voice with nothing playing before leaving, None if it never leaves
+    pub(crate) async fn idle_timeout(&self, guild_id: GuildId) -> Option<Duration> {
+        match self.get(guild_id, |settings| settings.idle_timeout).await {
+            Some(0) => None,
+            Some(minutes) => Some(Duration::from_secs(minutes * 60)),
+            None => Some(self.default_idle_timeout),
+        }
+    }
+
+    // Reads a value from a guild's settings, using the defaults for unconfigured guilds
+    pub(crate) async fn get<T>(&self, guild_id: GuildId, f: impl FnOnce(&GuildSettings) -> T) -> T {
+        let map = self.map.read().await;
+
+        match map.get(&guild_id.0) {
+            Some(settings) => f(settings),
+            None => f(&GuildSettings::default()),
+        }
+    }
+
+    // Changes a guild's settings and writes all settings back to disk
+    pub(crate) async fn update<T>(&self, guild_id: GuildId, f: impl FnOnce(&mut GuildSettings) -> T) -> T {
+        let mut map = self.map.write().await;
+        let result = f(map.entry(guild_id.0).or_default());
+
+        match serde_json::to_vec_pretty(&*map) {
+            Ok(bytes) => {
+                if let Err(why) = tokio::fs::write(&self.path, bytes).await {
+                    println!("Error saving guild settings: {:?}", why);
+                }
+            }
+            Err(why) => println!("Error serializing guild settings: {:?}", why),
+        }
+
+        result
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serenity::prelude::TypeMapKey;
+use tokio::sync::RwLock;
+
+use crate::{Song, SongSource};
+
+// The YouTube video a Spotify track resolved to
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct SpotifyMatch {
+    title: String,
+    artist: String,
+    duration: Duration,
+    url: String,
+}
+
+impl SpotifyMatch {
+    pub(crate) fn from_song(song: &Song) -> SpotifyMatch {
+        SpotifyMatch {
+            title: song.title.clone(),
+            artist: song.artist.clone(),
+            duration: song.duration,
+            url: song.source.url().to_owned(),
+        }
+    }
+
+    pub(crate) fn as_song(&self, author: String) -> Song {
+        Song::new(self.title.clone(), self.artist.clone(), author, self.duration, SongSource::YouTube { url: self.url.clone() })
+    }
+}
+
+pub(crate) struct SpotifyCacheKey;
+impl TypeMapKey for SpotifyCacheKey {
+    type Value = Arc<SpotifyCache>;
+}
+
+// Spotify track ids and their matches, saved to disk so looping the same playlists doesn't use up search quota
+pub(crate) struct SpotifyCache {
+    path: PathBuf,
+    map: RwLock<HashMap<String, SpotifyMatch>>,
+}
+
+impl SpotifyCache {
+    // Loads the cache file, or starts empty if it doesn't exist yet
+    pub(crate) fn load(path: PathBuf) -> SpotifyCache {
+        let map = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).expect("Error parsing Spotify cache file"),
+            Err(_) => HashMap::new(),
+        };
+
+        SpotifyCache { path, map: RwLock::new(map) }
+    }
+
+    pub(crate) async fn get(&self, track_id: &str) -> Option<SpotifyMatch> {
+        self.map.read().await.get(track_id).cloned()
+    }
+
+    // Remembers a match and writes the whole cache back to disk
+    pub(crate) async fn insert(&self, track_id: String, spotify_match: SpotifyMatch) {
+        let mut map = self.map.write().await;
+        map.insert(track_id, spotify_match);
+
+        match serde_json::to_vec(&*map) {
+            Ok(bytes) => {
+                if let Err(why) = tokio::fs::write(&self.path, bytes).await {
+                    println!("Error saving Spotify cache: {:?}", why);
+                }
+            }
+            Err(why) => println!("Error serializing Spotify cache: {:?}", why),
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use serenity::prelude::TypeMapKey;
+
+#[derive(Default, Clone, Copy)]
+struct SourceCounts {
+    // lookups that found a song, and ones that didn't
+    resolved: u64,
+    unresolved: u64,
+    // songs that got past their first seconds, and ones that couldn't be opened or ended right away
+    played: u64,
+    failed: u64,
+}
+
+pub(crate) struct SourceStatsKey;
+impl TypeMapKey for SourceStatsKey {
+    type Value = Arc<SourceStats>;
+}
+
+// How well each kind of source resolves and plays, so operators can tell whether a
+// search query or provider needs changing. Served by the health endpoint at /metrics.
+#[derive(Default)]
+pub(crate) struct SourceStats {
+    // a BTreeMap so the sources are always listed in the same order
+    counts: Mutex<BTreeMap<&'static str, SourceCounts>>,
+}
+
+impl SourceStats {
+    pub(crate) fn record_lookup(&self, source: &'static str, found: bool) {
+        self.update(source, |counts| if found { counts.resolved += 1 } else { counts.unresolved += 1 });
+    }
+
+    pub(crate) fn record_playback(&self, source: &'static str, started: bool) {
+        self.update(source, |counts| if started { counts.played += 1 } else { counts.failed += 1 });
+    }
+
+    fn update(&self, source: &'static str, f: impl FnOnce(&mut SourceCounts)) {
+        f(self.counts.lock().unwrap().entry(source).or_default());
+    }
+
+    // The counters in the Prometheus text format
+    pub(crate) fn render(&self) -> String {
+        let counts = self.counts.lock().unwrap().clone();
+        let mut text = String::new();
+
+        text.push_str("# TYPE music_bot_lookups_total counter\n");
+        for (source, counts) in &counts {
+            text.push_str(&format!("music_bot_lookups_total{{source=\"{}\",result=\"resolved\"}} {}\n", source, counts.resolved));
+            text.push_str(&format!("music_bot_lookups_total{{source=\"{}\",result=\"unresolved\"}} {}\n", source, counts.unresolved));
+        }
+        text.push_str("# TYPE music_bot_playbacks_total counter\n");
+        for (source, counts) in &counts {
+            text.push_str(&format!("music_bot_playbacks_total{{source=\"{}\",result=\"started\"}} {}\n", source, counts.played));
+            text.push_str(&format!("music_bot_playbacks_total{{source=\"{}\",result=\"failed\"}} {}\n", source, counts.failed));
+        }
+        text
+    }
+}